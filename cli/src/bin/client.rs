@@ -1,4 +1,7 @@
-use binling_core::capsules::{Capsule, CapsuleHeader, SquareSpace};
+use binling_core::capsules::{Capsule, CapsuleHeader, ExecState, SquareSpace};
+use binling_core::codec::{LatticeCodec, HEADER_LEN};
+use binling_core::instructions::OpCode;
+use binling_core::integrity;
 use std::io::{Read, Write};
 use std::net::TcpStream;
 use std::thread;
@@ -25,23 +28,28 @@ fn main() -> std::io::Result<()> {
     // 3. Construct the "Architect" Capsule
     println!("> [GENETICS] Constructing Architect Payload...");
 
-    // 4,096 SPAWN instructions (0x07)
-    // This forces the Kernel to spawn 1 brick per cycle for 4096 cycles.
-    let payload = vec![7u8; 4096];
+    // SPAWN d d 0 0 0 (0x08) for d = 1..=64, then HALT. SPAWN ends the
+    // activation, so the Kernel lays one blank brick per cycle along the
+    // x = y diagonal, clear of the Star Fortress arms, for 64 cycles.
+    let mut payload = Vec::new();
+    for d in 1..=64u8 {
+        payload.extend_from_slice(&[OpCode::SPAWN as u8, d, d, 0, 0, 0]);
+    }
+    payload.push(OpCode::HALT as u8);
 
-    let capsule = Capsule {
+    let mut capsule = Capsule {
         header: CapsuleHeader {
             magic: *b"BLE1",
             version_major: 0,
             version_minor: 1,
             flags: 0,
-            capsule_id: 777, // Kernel ID
+            capsule_id: 7777, // Ids below 1000 are reserved for the host
             ss_n: SquareSpace::SS64,
             priority: 10,
             coord_x: 0,
             coord_y: 0,
             coord_z: 0,
-            header_len: 122,
+            header_len: HEADER_LEN as u16,
             policy_len: 0,
             payload_len: payload.len() as u32,
             pad_len: 0,
@@ -51,10 +59,12 @@ fn main() -> std::io::Result<()> {
         },
         policy_core: vec![],
         payload,
+        state: ExecState::default(),
     };
 
-    // 4. Serialize
-    let encoded = bincode::serialize(&capsule).expect("Failed to serialize");
+    // 4. Seal & Encode (BLE wire format)
+    integrity::seal(&mut capsule).expect("Failed to seal");
+    let encoded = LatticeCodec::encode(&capsule).expect("Failed to encode");
 
     // 5. Send Immediately with Force Push
     println!("> [SEND] Teleporting Capsule ({} bytes)...", encoded.len());
@@ -68,10 +78,8 @@ fn main() -> std::io::Result<()> {
 
     // Optional: Peek for response (don't block if none)
     let mut response_buffer = Vec::new();
-    if let Ok(_) = stream.read_to_end(&mut response_buffer) {
-        if !response_buffer.is_empty() {
-            println!("> [ACK] Server acknowledged receipt.");
-        }
+    if stream.read_to_end(&mut response_buffer).is_ok() && !response_buffer.is_empty() {
+        println!("> [ACK] Server acknowledged receipt.");
     }
 
     println!("> [DONE] Disconnecting.");
@@ -1,5 +1,10 @@
-use binling_core::capsules::{Capsule, CapsuleHeader, SquareSpace};
-use binling_core::instructions::OpCode;
+use binling_core::asm;
+use binling_core::capsules::{Capsule, CapsuleHeader, ExecState, SquareSpace};
+use binling_core::codec::LatticeCodec;
+use binling_core::events::VmEvent;
+use binling_core::integrity;
+use binling_core::scenario::Scenario;
+use binling_core::trace::{self, Trace};
 use binling_core::vm::LatticeVM;
 use serde_json::json;
 use std::env;
@@ -12,41 +17,52 @@ use tokio::sync::broadcast;
 
 mod ws_server;
 
-// --- THE ROSETTA STONE (ASSEMBLER) ---
-fn compile_line(line: &str) -> Vec<u8> {
-    let parts: Vec<&str> = line.trim().split_whitespace().collect();
-    let mut bytecode = Vec::new();
-
-    for part in parts {
-        match part.to_uppercase().as_str() {
-            // INSTRUCTIONS
-            "NOOP" => bytecode.push(OpCode::NOOP as u8), // 0
-            "HALT" => bytecode.push(OpCode::HALT as u8), // 1
-            "ADD" => bytecode.push(OpCode::ADD as u8),   // 3
-            "SUB" => bytecode.push(OpCode::SUB as u8),   // 4
-            "INC" => bytecode.push(OpCode::INC as u8),   // 5
-            "DEC" => bytecode.push(OpCode::DEC as u8),   // 6
-            "LOG" => bytecode.push(OpCode::LOG as u8),   // 7
-            "SPAWN" => bytecode.push(OpCode::SPAWN as u8), // 8
-            "STORE" => bytecode.push(OpCode::STORE as u8), // 9
-            "LOAD" => bytecode.push(OpCode::LOAD as u8), // 10
-            "JMP" => bytecode.push(OpCode::JMP as u8),   // 11
-            "BEQ" => bytecode.push(OpCode::BEQ as u8),   // 12
-            "REPL" => bytecode.push(OpCode::REPL as u8), // 13
-            "VOID" => bytecode.push(OpCode::VOID as u8), // 14
-
-            // NUMBERS (LITERALS - NOW SUPPORTS NEGATIVES)
-            _ => {
-                // Parse as i8 (allows -128 to 127) then cast to u8 byte
-                if let Ok(num) = part.parse::<i8>() {
-                    bytecode.push(num as u8);
-                } else {
-                    println!("!! [ASM ERROR] Unknown Token: {}", part);
+// A built-in scenario name, or the path of a scenario JSON file
+fn load_scenario(arg: &str) -> Result<Scenario, Box<dyn std::error::Error>> {
+    if Scenario::BUILTIN.contains(&arg) {
+        return Ok(Scenario::builtin(arg)?);
+    }
+    Ok(Scenario::from_json(&fs::read_to_string(arg)?)?)
+}
+
+// Time-travel commands written to oracle_in.txt instead of BASM:
+//   @BACK            step one cycle back
+//   @SEEK <cycle>    rewind to, or run forward to, a cycle
+//   @FORK <cycle> <name>   save the state after <cycle> as universe_<name>.bin
+fn oracle_command(vm: &mut LatticeVM, line: &str) -> String {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let cycle = |i: usize| parts.get(i).and_then(|s| s.parse::<u64>().ok());
+    let result = match (parts[0].to_uppercase().as_str(), parts.len()) {
+        ("@BACK", 1) => vm
+            .step_back()
+            .map(|_| format!("Back at cycle {}", vm.cycle_count)),
+        ("@SEEK", 2) => match cycle(1) {
+            Some(n) => vm
+                .seek(n)
+                .map(|_| format!("Now at cycle {}", vm.cycle_count)),
+            None => return "usage: @SEEK <cycle>".to_string(),
+        },
+        ("@FORK", 3) => match cycle(1) {
+            Some(n) => vm.fork_at(n, parts[2].to_string()).map(|fork| {
+                let file = format!("universe_{}.bin", parts[2]);
+                match fork.save_world(&file) {
+                    Ok(()) => format!("Forked cycle {} to {}", fork.cycle_count, file),
+                    Err(e) => format!("Fork not saved: {}", e),
                 }
-            }
-        }
+            }),
+            None => return "usage: @FORK <cycle> <name>".to_string(),
+        },
+        _ => return format!("Unknown command '{}'", line),
+    };
+    result.unwrap_or_else(|e| format!("Refused: {}", e))
+}
+
+// Saves the universe, and the trace next to it when one is being recorded
+fn checkpoint(vm: &LatticeVM, filename: &str, trace_file: &str) {
+    let _ = vm.save_world(filename);
+    if let Some(trace) = vm.trace() {
+        let _ = trace.save(trace_file);
     }
-    bytecode
 }
 
 #[tokio::main]
@@ -54,14 +70,50 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("=== BinLing CLI v1.4 (Memory Enabled) ===");
 
     // 1. DETERMINE IDENTITY
-    let args: Vec<String> = env::args().collect();
-    let universe_id = if args.len() > 1 {
-        args[1].clone()
-    } else {
-        "default".to_string()
-    };
+    // Usage: binling_cli [universe_id] [--scenario <name|file.json>] [--trace]
+    //                    [--parallel] [--history <snapshots>]
+    //        binling_cli --replay <universe_id.trace>
+    // The scenario only applies when the universe does not exist yet.
+    // --trace records injections and per-cycle state digests to
+    // universe_<id>.trace; --replay re-runs such a file and checks them.
+    // --parallel steps capsules on all cores, with identical results.
+    // --history keeps a snapshot every 10 cycles, at most <snapshots> of
+    // them, for @BACK / @SEEK / @FORK. Each is a full copy of the lattice.
+    let mut universe_id = "default".to_string();
+    let mut scenario_arg = "star-fortress".to_string();
+    let mut record_trace = false;
+    let mut parallel = false;
+    let mut history = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scenario" => {
+                scenario_arg = args.next().ok_or("--scenario needs a name or file")?;
+            }
+            "--trace" => record_trace = true,
+            "--parallel" => parallel = true,
+            "--history" => {
+                let snapshots = args.next().ok_or("--history needs a snapshot count")?;
+                history = Some(snapshots.parse::<usize>()?);
+            }
+            "--replay" => {
+                let path = args.next().ok_or("--replay needs a trace file")?;
+                let trace = Trace::load(&path)?;
+                let replayed = trace.replay()?;
+                println!(
+                    "> [TRACE] Replay matched {} cycles. Cycle {}, digest {}",
+                    trace.digests.len(),
+                    replayed.cycle_count,
+                    trace::hex(&replayed.state_digest())
+                );
+                return Ok(());
+            }
+            _ => universe_id = arg,
+        }
+    }
 
     let filename = format!("universe_{}.bin", universe_id);
+    let trace_file = format!("universe_{}.trace", universe_id);
     let interface_dir = "./interface";
     let input_file = format!("{}/oracle_in.txt", interface_dir);
     let output_file = format!("{}/oracle_out.txt", interface_dir);
@@ -81,12 +133,41 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             );
             Arc::new(Mutex::new(loaded_vm))
         }
-        Err(_) => {
-            println!("> [VAULT] New World Created.");
-            Arc::new(Mutex::new(LatticeVM::new(universe_id.clone())))
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let scenario = load_scenario(&scenario_arg)?;
+            let fresh = LatticeVM::with_scenario(universe_id.clone(), &scenario)?;
+            println!(
+                "> [VAULT] New World Created from '{}'. Nodes: {}",
+                scenario.name,
+                fresh.next_queue.len()
+            );
+            Arc::new(Mutex::new(fresh))
+        }
+        Err(e) => {
+            // Don't start a fresh world that would overwrite the file on save
+            eprintln!("!! [VAULT] Cannot load {}: {}", filename, e);
+            return Err(e.into());
         }
     };
 
+    if let Some(snapshots) = history {
+        vm.lock().unwrap().enable_history(10, snapshots);
+        println!(
+            "> [HISTORY] Keeping the last {} cycles for @BACK / @SEEK / @FORK",
+            10 * snapshots.max(1)
+        );
+    }
+
+    if parallel {
+        vm.lock().unwrap().set_parallel(true);
+        println!("> [SYSTEM] Parallel executor enabled");
+    }
+
+    if record_trace {
+        vm.lock().unwrap().start_trace();
+        println!("> [TRACE] Recording to {}", trace_file);
+    }
+
     // 3. Setup Broadcast
     let (tx_status, _rx_status) = broadcast::channel(100);
 
@@ -109,16 +190,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 let vm_clone = vm_for_net.clone();
                 tokio::spawn(async move {
                     let mut buffer = Vec::new();
-                    if let Ok(_) = socket.read_to_end(&mut buffer).await {
-                        if !buffer.is_empty() {
-                            if let Ok(c) = bincode::deserialize::<Capsule>(&buffer) {
+                    if socket.read_to_end(&mut buffer).await.is_ok() && !buffer.is_empty() {
+                        match LatticeCodec::decode(&buffer) {
+                            Ok(c) => {
                                 println!(
-                                    ">> [NET] Recv Capsule {}. Forwarding...",
+                                    ">> [NET] Recv Capsule {}. Verifying...",
                                     c.header.capsule_id
                                 );
                                 let mut locked_vm = vm_clone.lock().unwrap();
-                                locked_vm.activate(c);
+                                if let Err(e) = locked_vm.activate(c) {
+                                    println!("!! [NET] {}", e);
+                                }
                             }
+                            Err(e) => println!("!! [NET] Rejected BLE frame: {}", e),
                         }
                     }
                 });
@@ -137,27 +221,42 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             if Path::new(&input_path).exists() {
                 if let Ok(content) = fs::read_to_string(&input_path) {
-                    if !content.trim().is_empty() {
+                    if content.trim().starts_with('@') {
+                        let reply = {
+                            let mut locked_vm = vm_for_oracle.lock().unwrap();
+                            oracle_command(&mut locked_vm, content.trim())
+                        };
+                        println!(">> [ORACLE] {}", reply);
+                        let _ = fs::write(&input_path, "");
+                    } else if !content.trim().is_empty() {
                         println!(">> [ASM] Compiling: '{}'", content.trim());
 
-                        let payload = compile_line(&content);
+                        let payload = match asm::assemble(&content) {
+                            Ok(payload) => payload,
+                            Err(e) => {
+                                println!("!! [ASM ERROR] {}", e);
+                                Vec::new()
+                            }
+                        };
 
                         if !payload.is_empty() {
                             let payload_len = payload.len();
 
-                            // --- TARGET: ID 999 (USER SPACE CORE RUNNER) ---
-                            let capsule = Capsule {
+                            // --- TARGET: USER SPACE CORE RUNNER ---
+                            // Each injection gets a fresh CAPSULE_ID from the VM, otherwise
+                            // the scheduler would de-duplicate it against the previous one.
+                            let mut capsule = Capsule {
                                 header: CapsuleHeader {
                                     magic: *b"BLE1",
                                     version_major: 0,
                                     version_minor: 1,
                                     flags: 1,
-                                    capsule_id: 999,
+                                    capsule_id: 0,
                                     ss_n: SquareSpace::SS64,
                                     priority: 100,
                                     coord_x: 0,
                                     coord_y: 0,
-                                    coord_z: 0, // In the Core
+                                    coord_z: 0,
                                     header_len: 0,
                                     policy_len: 0,
                                     payload_len: payload_len as u32,
@@ -167,15 +266,39 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                     capsule_hash: [0; 32],
                                 },
                                 policy_core: vec![],
-                                payload: payload,
+                                payload,
+                                state: ExecState::default(),
                             };
 
-                            {
+                            let result = {
                                 let mut locked_vm = vm_for_oracle.lock().unwrap();
-                                locked_vm.activate(capsule);
-                            }
+                                match locked_vm.allocate_id() {
+                                    Some(id) => {
+                                        capsule.header.capsule_id = id;
+                                        // The origin, or the nearest free cell when cells are exclusive
+                                        (
+                                            capsule.header.coord_x,
+                                            capsule.header.coord_y,
+                                            capsule.header.coord_z,
+                                        ) = locked_vm.free_cell_near((0, 0, 0));
+                                        integrity::seal(&mut capsule)
+                                            .map_err(|e| e.to_string())
+                                            .and_then(|_| {
+                                                locked_vm
+                                                    .activate(capsule)
+                                                    .map_err(|e| e.to_string())
+                                            })
+                                    }
+                                    None => Err("no capsule ids are left".to_string()),
+                                }
+                            };
 
-                            println!(">> [ORACLE] Injected {} bytes to CORE.", payload_len);
+                            match result {
+                                Ok(()) => {
+                                    println!(">> [ORACLE] Injected {} bytes to CORE.", payload_len)
+                                }
+                                Err(e) => println!("!! [ORACLE] Injection refused: {}", e),
+                            }
                         }
 
                         let _ = fs::write(&input_path, "");
@@ -212,6 +335,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
 
+            // EVENT BRIDGE (per-cycle summaries only go to the dashboard).
+            // Traps also go to the oracle, so whoever wrote the BASM sees them.
+            let events: Vec<VmEvent> = vm.events.drain(..).collect();
+            for event in &events {
+                if !matches!(event, VmEvent::CycleCompleted { .. }) {
+                    println!("{}", event);
+                }
+                if let VmEvent::Trapped { .. } = event {
+                    use std::io::Write;
+                    if let Ok(mut file) = fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&output_file)
+                    {
+                        let _ = writeln!(file, "TRAP: {}", event);
+                    }
+                }
+            }
+
             // Cast u16 flag to u8
             let cell_data: Vec<(i32, i32, i32, u8)> = vm
                 .next_queue
@@ -229,16 +371,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let payload = json!({
                 "cycle": vm.cycle_count,
                 "active_count": vm.next_queue.len(),
-                "cells": cell_data
+                "cells": cell_data,
+                "events": events
             });
 
             let _ = tx_status.send(payload.to_string());
 
-            if !vm.is_void() {
-                vm.next_cycle();
-
-                if vm.cycle_count % 50 == 0 {
-                    let _ = vm.save_world(&filename);
+            if !vm.is_void() && !vm.is_halted() {
+                if let Err(e) = vm.next_cycle() {
+                    // FAIL CLOSED: report once, keep the halted world on disk
+                    println!("!! [VM] HALTED at cycle {}: {}", vm.cycle_count, e);
+                    use std::io::Write;
+                    if let Ok(mut file) = fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&output_file)
+                    {
+                        let _ = writeln!(file, "HALT: {}", e);
+                    }
+                    checkpoint(&vm, &filename, &trace_file);
+                } else if vm.cycle_count % 50 == 0 {
+                    checkpoint(&vm, &filename, &trace_file);
                 }
             }
         }
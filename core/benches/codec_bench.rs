@@ -1,8 +1,8 @@
 // Only compile the imports if "cli-mode" is enabled
 #[cfg(feature = "cli-mode")]
-use binling_core::capsules::{Capsule, CapsuleHeader, SquareSpace};
+use binling_core::capsules::{Capsule, CapsuleHeader, ExecState, SquareSpace};
 #[cfg(feature = "cli-mode")]
-use binling_core::codec::LatticeCodec;
+use binling_core::codec::{LatticeCodec, HEADER_LEN};
 #[cfg(feature = "cli-mode")]
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
@@ -11,7 +11,7 @@ use criterion::{black_box, criterion_group, criterion_main, Criterion};
 #[cfg(feature = "cli-mode")]
 fn benchmark_codec(c: &mut Criterion) {
     // 1. Setup
-    let capsule = Capsule {
+    let mut capsule = Capsule {
         header: CapsuleHeader {
             magic: *b"BLE1",
             version_major: 0,
@@ -19,7 +19,7 @@ fn benchmark_codec(c: &mut Criterion) {
             flags: 0,
             ss_n: SquareSpace::SS64,
             priority: 10,
-            header_len: 122,
+            header_len: HEADER_LEN as u16,
             policy_len: 0,
             payload_len: 0,
             pad_len: 0,
@@ -33,11 +33,22 @@ fn benchmark_codec(c: &mut Criterion) {
         },
         policy_core: vec![0; 64],
         payload: vec![0; 128],
+        state: ExecState::default(),
     };
 
+    LatticeCodec::fill_lengths(&mut capsule).unwrap();
+
     // Pre-encode for the decode test
     let encoded_bytes = LatticeCodec::encode(&capsule).unwrap();
 
+    // Sanity: fixed framing and lossless round trip
+    assert_eq!(&encoded_bytes[0..4], b"BLE1");
+    assert_eq!(encoded_bytes.len(), capsule.capacity() as usize);
+    let decoded = LatticeCodec::decode(&encoded_bytes).unwrap();
+    assert_eq!(decoded.header, capsule.header);
+    assert_eq!(decoded.policy_core, capsule.policy_core);
+    assert_eq!(decoded.payload, capsule.payload);
+
     // 2. Benchmark Encoding
     c.bench_function("codec_encode", |b| {
         b.iter(|| LatticeCodec::encode(black_box(&capsule)))
@@ -47,6 +58,19 @@ fn benchmark_codec(c: &mut Criterion) {
     c.bench_function("codec_decode", |b| {
         b.iter(|| LatticeCodec::decode(black_box(&encoded_bytes)))
     });
+
+    // 4. Header Overhead (Suite A2): an empty capsule is exactly HEADER_LEN
+    // bytes of framing plus padding.
+    let mut empty = capsule.clone();
+    empty.policy_core.clear();
+    empty.payload.clear();
+    let empty_bytes = LatticeCodec::encode(&empty).unwrap();
+    let framed = LatticeCodec::decode(&empty_bytes).unwrap();
+    assert_eq!(framed.header.header_len as usize, HEADER_LEN);
+    assert_eq!(
+        framed.header.pad_len as usize,
+        empty_bytes.len() - HEADER_LEN
+    );
 }
 
 // Register the group ONLY if feature is on
@@ -1,9 +1,15 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 
 #[cfg(feature = "cli-mode")]
-use binling_core::capsules::{Capsule, CapsuleHeader, SquareSpace};
+use binling_core::capsules::{Capsule, CapsuleHeader, ExecState, SquareSpace};
 #[cfg(feature = "cli-mode")]
-use binling_core::vm::LatticeVM;
+use binling_core::codec::HEADER_LEN;
+#[cfg(feature = "cli-mode")]
+use binling_core::instructions::OpCode;
+#[cfg(feature = "cli-mode")]
+use binling_core::integrity;
+#[cfg(feature = "cli-mode")]
+use binling_core::vm::{LatticeVM, FIRST_USER_ID};
 
 #[cfg(feature = "cli-mode")]
 fn benchmark_physics_loop(c: &mut Criterion) {
@@ -13,22 +19,26 @@ fn benchmark_physics_loop(c: &mut Criterion) {
             let mut vm = LatticeVM::new("BENCHMARK_UNIVERSE".to_string());
 
             // 2. Create Kernel (Simplified payload)
-            let payload = vec![7u8; 10]; // 10 SPAWN instructions
-            let kernel = Capsule {
+            // 10 SPAWNs, one brick per cycle (SPAWN ends the activation)
+            // along the x = y diagonal, clear of the Star Fortress arms
+            let payload: Vec<u8> = (1..=10u8)
+                .flat_map(|d| [OpCode::SPAWN as u8, d, d, 0, 0, 0])
+                .collect();
+            let mut kernel = Capsule {
                 header: CapsuleHeader {
                     magic: *b"BLE1",
                     version_major: 0,
                     version_minor: 1,
                     flags: 0,
-                    capsule_id: 777,
+                    capsule_id: FIRST_USER_ID,
                     ss_n: SquareSpace::SS64,
                     priority: 10,
                     coord_x: 0,
                     coord_y: 0,
                     coord_z: 0,
-                    header_len: 122,
+                    header_len: HEADER_LEN as u16,
                     policy_len: 0,
-                    payload_len: 10,
+                    payload_len: payload.len() as u32,
                     pad_len: 0,
                     dict_hash: [0; 32],
                     policy_core_hash: [0; 32],
@@ -36,14 +46,16 @@ fn benchmark_physics_loop(c: &mut Criterion) {
                 },
                 policy_core: vec![],
                 payload,
+                state: ExecState::default(),
             };
 
-            // 3. Inject
-            vm.activate(kernel);
+            // 3. Seal & Inject
+            integrity::seal(&mut kernel).unwrap();
+            vm.activate(kernel).unwrap();
 
             // 4. Run 10 Cycles
             for _ in 0..10 {
-                vm.next_cycle();
+                let _ = vm.next_cycle();
             }
         })
     });
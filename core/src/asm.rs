@@ -0,0 +1,200 @@
+use crate::instructions::OpCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+// BinLing Assembly (BASM) -> payload bytes
+//
+// Whitespace-separated tokens, one byte each:
+//   mnemonic   the opcode byte (any OpCode, case-insensitive)
+//   R0-R3      the register index
+//   number     -128..=255; negatives are stored as two's complement (i8)
+//   label      the payload offset of a `label:` definition (0..=255)
+// A `label:` definition emits nothing. Labels are case-sensitive and may
+// not be spelled like a mnemonic or register.
+// Operand counts are not checked; the VM reads whatever bytes follow. The
+// operands after a mnemonic that the VM reads as signed (see
+// OpCode::operand_signed) must be -128..=127. A wide operand (see
+// OpCode::operand_wide) is two bytes, big-endian, and takes -32768..=65535,
+// or -32768..=32767 where it is signed.
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AsmError {
+    UnknownToken(String),
+    InvalidLabel(String),
+    DuplicateLabel(String),
+    UndefinedLabel(String),
+    // The label's offset does not fit in its operand
+    LabelOutOfRange { label: String, offset: usize },
+    // A value above 127 where the VM reads a signed byte, or above 32767
+    // where it reads a signed word
+    SignedOutOfRange { mnemonic: String, token: String },
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsmError::UnknownToken(t) => write!(f, "unknown token '{}'", t),
+            AsmError::InvalidLabel(l) => write!(f, "'{}' is not a valid label name", l),
+            AsmError::DuplicateLabel(l) => write!(f, "label '{}' is defined twice", l),
+            AsmError::UndefinedLabel(l) => write!(f, "label '{}' is not defined", l),
+            AsmError::LabelOutOfRange { label, offset } => {
+                write!(
+                    f,
+                    "label '{}' is at offset {}, too far for its operand",
+                    label, offset
+                )
+            }
+            AsmError::SignedOutOfRange {
+                mnemonic: name,
+                token,
+            } => {
+                let range = match mnemonic(name) {
+                    Some(op) if op.operand_wide(0) => "-32768..=32767",
+                    _ => "-128..=127",
+                };
+                write!(
+                    f,
+                    "'{}' is out of range for {}, which reads it as {}",
+                    token, name, range
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    // First pass: every token but a definition is one byte, or two for a
+    // wide operand
+    let mut labels = HashMap::new();
+    let mut offset = 0;
+    let mut operands = Operands::default();
+    for token in source.split_whitespace() {
+        match token.strip_suffix(':') {
+            Some(name) => {
+                if !is_label_name(name) {
+                    return Err(AsmError::InvalidLabel(name.to_string()));
+                }
+                if labels.insert(name, offset).is_some() {
+                    return Err(AsmError::DuplicateLabel(name.to_string()));
+                }
+            }
+            None => match operands.fill(token) {
+                Some((op, i)) if op.operand_wide(i) => offset += 2,
+                _ => offset += 1,
+            },
+        }
+    }
+
+    // Second pass
+    let mut bytes = Vec::with_capacity(offset);
+    let mut operands = Operands::default();
+    for token in source.split_whitespace().filter(|t| !t.ends_with(':')) {
+        let slot = operands.fill(token);
+        let (value, signed_max) = match slot {
+            Some((op, i)) if op.operand_wide(i) => {
+                let word = assemble_word(token, &labels)?;
+                bytes.extend(word.to_be_bytes());
+                (word, i16::MAX as u16)
+            }
+            _ => {
+                let byte = assemble_token(token, &labels)?;
+                bytes.push(byte);
+                (byte as u16, i8::MAX as u16)
+            }
+        };
+        // Only positive values (numbers or labels) exceed the signed
+        // maximum here; negatives were already range-checked
+        if let Some((op, i)) = slot {
+            if op.operand_signed(i) && value > signed_max && !token.starts_with('-') {
+                return Err(AsmError::SignedOutOfRange {
+                    mnemonic: op.mnemonic().to_string(),
+                    token: token.to_string(),
+                });
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+// Tracks which operand of the last mnemonic the next token fills
+#[derive(Default)]
+struct Operands(Option<(OpCode, usize)>);
+
+impl Operands {
+    // The (mnemonic, operand index) that `token` fills, if any
+    fn fill(&mut self, token: &str) -> Option<(OpCode, usize)> {
+        let slot = self.0;
+        self.0 = match slot {
+            Some((op, i)) => (i + 1 < op.operand_count()).then_some((op, i + 1)),
+            None => mnemonic(token)
+                .filter(|op| op.operand_count() > 0)
+                .map(|op| (op, 0)),
+        };
+        slot
+    }
+}
+
+fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let starts_well = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    starts_well && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') && keyword(name).is_none()
+}
+
+fn mnemonic(token: &str) -> Option<OpCode> {
+    let upper = token.to_uppercase();
+    OpCode::ALL.into_iter().find(|op| op.mnemonic() == upper)
+}
+
+// A mnemonic or register name
+fn keyword(token: &str) -> Option<u8> {
+    if let Some(op) = mnemonic(token) {
+        return Some(op as u8);
+    }
+    match token.to_uppercase().as_str() {
+        "R0" => Some(0),
+        "R1" => Some(1),
+        "R2" => Some(2),
+        "R3" => Some(3),
+        _ => None,
+    }
+}
+
+fn assemble_token(token: &str, labels: &HashMap<&str, usize>) -> Result<u8, AsmError> {
+    if let Some(byte) = keyword(token) {
+        return Ok(byte);
+    }
+    match token.parse::<i16>() {
+        Ok(n) if (-128..=255).contains(&n) => Ok(n as u8),
+        _ => match labels.get(token) {
+            Some(&offset) => u8::try_from(offset).map_err(|_| AsmError::LabelOutOfRange {
+                label: token.to_string(),
+                offset,
+            }),
+            None if is_label_name(token) => Err(AsmError::UndefinedLabel(token.to_string())),
+            None => Err(AsmError::UnknownToken(token.to_string())),
+        },
+    }
+}
+
+// assemble_token() for a wide operand
+fn assemble_word(token: &str, labels: &HashMap<&str, usize>) -> Result<u16, AsmError> {
+    if let Some(byte) = keyword(token) {
+        return Ok(byte as u16);
+    }
+    match token.parse::<i32>() {
+        Ok(n) if (-32768..=65535).contains(&n) => Ok(n as u16),
+        _ => match labels.get(token) {
+            Some(&offset) => u16::try_from(offset).map_err(|_| AsmError::LabelOutOfRange {
+                label: token.to_string(),
+                offset,
+            }),
+            None if is_label_name(token) => Err(AsmError::UndefinedLabel(token.to_string())),
+            None => Err(AsmError::UnknownToken(token.to_string())),
+        },
+    }
+}
@@ -1,4 +1,7 @@
+use crate::lattice::Coord;
+use crate::trap::Trap;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 
 // The fixed set of allowed Cube sizes (Spec v0.1 Section 4.2)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -11,6 +14,19 @@ pub enum SquareSpace {
     SS128 = 128,
 }
 
+impl SquareSpace {
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            8 => Some(SquareSpace::SS8),
+            16 => Some(SquareSpace::SS16),
+            32 => Some(SquareSpace::SS32),
+            64 => Some(SquareSpace::SS64),
+            128 => Some(SquareSpace::SS128),
+            _ => None,
+        }
+    }
+}
+
 // The normative Fixed Header (Spec v0.1 Section 6.1)
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CapsuleHeader {
@@ -41,12 +57,75 @@ pub struct CapsuleHeader {
     pub capsule_hash: [u8; 32],     // Full Integrity Hash
 }
 
+// Where a capsule is in its life inside the VM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CapsuleStatus {
+    #[default]
+    Running,
+    Halted, // Executed HALT: stays on the lattice, no longer steps
+    Voided, // Executed VOID: removed at the end of the cycle
+    Faulted, // Hit a trap with no fault handler armed (see ExecState::fault)
+    Dormant, // Waiting for an ACTIVATE; does not step until then
+}
+
+// What a capsule is for. Assigned by the VM, never read from the wire, so
+// an injected capsule cannot make itself the oracle by picking an id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CapsuleRole {
+    Structure,    // Inert building block; dormant until activated
+    OracleOutput, // Emits what is written into it to the output buffer
+    #[default]
+    UserProgram, // Anything admitted through activate()
+    Kernel,      // Host-installed program
+    Device,      // Driven by the host; the VM never steps it
+}
+
+impl CapsuleRole {
+    // Status a newly created capsule of this role starts in
+    pub fn initial_status(self) -> CapsuleStatus {
+        match self {
+            CapsuleRole::Structure => CapsuleStatus::Dormant,
+            _ => CapsuleStatus::Running,
+        }
+    }
+}
+
+// Runtime state owned by the VM. Never part of the header or the hashes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecState {
+    pub ip: u32,             // Offset of the next instruction in the payload
+    pub registers: [i32; 4], // R0-R3, private to this capsule
+    pub status: CapsuleStatus,
+    pub wait: u32, // Cycles left to sleep (WAIT)
+    pub replications: u32, // REPL/SPAWN performed, for HARD_CONSTRAINTS
+    pub role: CapsuleRole,
+    pub fault: Option<Trap>,  // Most recent trap taken
+    pub handler: Option<u32>, // Armed fault handler address (ONFAULT)
+    pub mailbox: VecDeque<Message>, // Delivered, not yet taken by RECV
+    pub call_stack: Vec<u32>, // Return addresses pushed by CALL
+    pub flags: u8,            // FLAG_EQUAL, FLAG_LESS, FLAG_BLOCKED
+}
+
+// ExecState::flags after CMP a b
+pub const FLAG_EQUAL: u8 = 1; // a == b
+pub const FLAG_LESS: u8 = 2; // a < b, signed
+// ...and after the cycle boundary that resolved a REPL, SPAWN or MOVE
+pub const FLAG_BLOCKED: u8 = 4; // It found its cell taken (see BBLK)
+
+// A value sent with SEND, waiting in the receiver's mailbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Message {
+    pub from: u32, // Sender CAPSULE_ID
+    pub value: i32,
+}
+
 // The complete Capsule structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Capsule {
     pub header: CapsuleHeader,
     pub policy_core: Vec<u8>,   // Canonical Q0-Q2 bytes
     pub payload: Vec<u8>,       // ASCII Instruction Stream
+    pub state: ExecState,       // Execution state (not serialized on the wire)
     // Note: Padding is generated during serialization, not stored here.
 }
 
@@ -56,4 +135,9 @@ impl Capsule {
         let n = self.header.ss_n as u32;
         n * n * n
     }
+
+    // Lattice cell this capsule occupies
+    pub fn coord(&self) -> Coord {
+        (self.header.coord_x, self.header.coord_y, self.header.coord_z)
+    }
 }
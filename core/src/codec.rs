@@ -1,20 +1,208 @@
-use crate::capsules::Capsule;
-use anyhow::Result; // We need to add 'anyhow' to Cargo.toml later if not present, but for now we use standard Result
+use crate::capsules::{Capsule, CapsuleHeader, ExecState, SquareSpace};
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
 // The Codec Module (Spec v0.1 Section 5)
-// Handles converting Capsules <-> Raw Bytes
+// Handles converting Capsules <-> Raw Bytes in the BLE wire format.
+//
+// Layout (all integers big-endian, see spec/binling_encoding_v0_1.md):
+//   [0..128)   Fixed header
+//   POLICY_LEN bytes of canonical Policy Core (Q0-Q2)
+//   PAYLOAD_LEN bytes of instruction stream (Q3+)
+//   PAD_LEN bytes of 0x00, filling the capsule up to its SquareSpace capacity
+
+pub const MAGIC: [u8; 4] = *b"BLE1";
+pub const HEADER_LEN: usize = 128;
+
+// Offsets of the fixed header fields
+const OFF_PAD_LEN: usize = 4;
+const OFF_POLICY_LEN: usize = 8;
+const OFF_VERSION_MAJOR: usize = 10;
+const OFF_VERSION_MINOR: usize = 11;
+const OFF_FLAGS: usize = 12;
+const OFF_SS_N: usize = 14;
+const OFF_PRIORITY: usize = 15;
+const OFF_HEADER_LEN: usize = 16;
+const OFF_PAYLOAD_LEN: usize = 18;
+const OFF_COORD_X: usize = 22;
+const OFF_COORD_Y: usize = 24;
+const OFF_COORD_Z: usize = 26;
+const OFF_CAPSULE_ID: usize = 28;
+const OFF_DICT_HASH: usize = 32;
+const OFF_POLICY_CORE_HASH: usize = 64;
+pub const OFF_CAPSULE_HASH: usize = 96;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CodecError {
+    TooShort(usize),
+    BadMagic,
+    UnsupportedVersion(u8, u8),
+    BadSquareSpace(u8),
+    PolicyTooLong(usize),
+    // Header + policy + payload do not fit in the SquareSpace
+    CapacityExceeded { needed: usize, capacity: usize },
+    // A length field disagrees with the data around it
+    HeaderLenMismatch,
+    PadLenMismatch,
+    SizeMismatch, // Frame is not SS_N^3 bytes
+    NonZeroPadding,
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::TooShort(len) => write!(f, "{} bytes is shorter than the header", len),
+            CodecError::BadMagic => write!(f, "missing BLE1 magic"),
+            CodecError::UnsupportedVersion(major, minor) => {
+                write!(f, "unsupported BLE version {}.{}", major, minor)
+            }
+            CodecError::BadSquareSpace(n) => write!(f, "invalid SS_N {}", n),
+            CodecError::PolicyTooLong(len) => write!(f, "policy core of {} bytes", len),
+            CodecError::CapacityExceeded { needed, capacity } => {
+                write!(
+                    f,
+                    "capsule needs {} bytes, capacity is {}",
+                    needed, capacity
+                )
+            }
+            CodecError::HeaderLenMismatch => write!(f, "HEADER_LEN does not match POLICY_LEN"),
+            CodecError::PadLenMismatch => write!(f, "PAD_LEN does not match the frame length"),
+            CodecError::SizeMismatch => write!(f, "frame length is not SS_N^3"),
+            CodecError::NonZeroPadding => write!(f, "padding bytes must be 0x00"),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
 
 pub struct LatticeCodec;
 
 impl LatticeCodec {
+    // Recomputes HEADER_LEN, POLICY_LEN, PAYLOAD_LEN and PAD_LEN from the
+    // capsule's contents. encode() always writes these computed values.
+    pub fn fill_lengths(capsule: &mut Capsule) -> Result<(), CodecError> {
+        let (policy_len, payload_len, pad_len) = Self::layout(capsule)?;
+        capsule.header.header_len = (HEADER_LEN + policy_len) as u16;
+        capsule.header.policy_len = policy_len as u16;
+        capsule.header.payload_len = payload_len as u32;
+        capsule.header.pad_len = pad_len as u32;
+        Ok(())
+    }
+
+    fn layout(capsule: &Capsule) -> Result<(usize, usize, usize), CodecError> {
+        let policy_len = capsule.policy_core.len();
+        if HEADER_LEN + policy_len > u16::MAX as usize {
+            return Err(CodecError::PolicyTooLong(policy_len));
+        }
+        let payload_len = capsule.payload.len();
+        let needed = HEADER_LEN + policy_len + payload_len;
+        let capacity = capsule.capacity() as usize;
+        if needed > capacity {
+            return Err(CodecError::CapacityExceeded { needed, capacity });
+        }
+        Ok((policy_len, payload_len, capacity - needed))
+    }
+
     // Encode: Capsule -> Bytes
-    pub fn encode(capsule: &Capsule) -> Result<Vec<u8>, bincode::Error> {
-        bincode::serialize(capsule)
+    pub fn encode(capsule: &Capsule) -> Result<Vec<u8>, CodecError> {
+        let (policy_len, payload_len, pad_len) = Self::layout(capsule)?;
+        let h = &capsule.header;
+
+        let mut out = Vec::with_capacity(capsule.capacity() as usize);
+        out.extend_from_slice(&h.magic);
+        out.extend_from_slice(&(pad_len as u32).to_be_bytes());
+        out.extend_from_slice(&(policy_len as u16).to_be_bytes());
+        out.push(h.version_major);
+        out.push(h.version_minor);
+        out.extend_from_slice(&h.flags.to_be_bytes());
+        out.push(h.ss_n as u8);
+        out.push(h.priority);
+        out.extend_from_slice(&((HEADER_LEN + policy_len) as u16).to_be_bytes());
+        out.extend_from_slice(&(payload_len as u32).to_be_bytes());
+        out.extend_from_slice(&h.coord_x.to_be_bytes());
+        out.extend_from_slice(&h.coord_y.to_be_bytes());
+        out.extend_from_slice(&h.coord_z.to_be_bytes());
+        out.extend_from_slice(&h.capsule_id.to_be_bytes());
+        out.extend_from_slice(&h.dict_hash);
+        out.extend_from_slice(&h.policy_core_hash);
+        out.extend_from_slice(&h.capsule_hash);
+        debug_assert_eq!(out.len(), HEADER_LEN);
+
+        out.extend_from_slice(&capsule.policy_core);
+        out.extend_from_slice(&capsule.payload);
+        out.resize(out.len() + pad_len, 0);
+        Ok(out)
     }
 
     // Decode: Bytes -> Capsule
-    pub fn decode(data: &[u8]) -> Result<Capsule, bincode::Error> {
-        bincode::deserialize(data)
+    // Only checks framing. Hash verification is a separate step.
+    pub fn decode(data: &[u8]) -> Result<Capsule, CodecError> {
+        if data.len() < HEADER_LEN {
+            return Err(CodecError::TooShort(data.len()));
+        }
+        if !Self::verify_header(data) {
+            return Err(CodecError::BadMagic);
+        }
+
+        let header = CapsuleHeader {
+            magic: MAGIC,
+            version_major: data[OFF_VERSION_MAJOR],
+            version_minor: data[OFF_VERSION_MINOR],
+            flags: read_u16(data, OFF_FLAGS),
+            ss_n: SquareSpace::from_u8(data[OFF_SS_N])
+                .ok_or(CodecError::BadSquareSpace(data[OFF_SS_N]))?,
+            priority: data[OFF_PRIORITY],
+            header_len: read_u16(data, OFF_HEADER_LEN),
+            policy_len: read_u16(data, OFF_POLICY_LEN),
+            payload_len: read_u32(data, OFF_PAYLOAD_LEN),
+            pad_len: read_u32(data, OFF_PAD_LEN),
+            coord_x: read_u16(data, OFF_COORD_X) as i16,
+            coord_y: read_u16(data, OFF_COORD_Y) as i16,
+            coord_z: read_u16(data, OFF_COORD_Z) as i16,
+            capsule_id: read_u32(data, OFF_CAPSULE_ID),
+            dict_hash: read_hash(data, OFF_DICT_HASH),
+            policy_core_hash: read_hash(data, OFF_POLICY_CORE_HASH),
+            capsule_hash: read_hash(data, OFF_CAPSULE_HASH),
+        };
+        if header.version_major != 0 {
+            return Err(CodecError::UnsupportedVersion(
+                header.version_major,
+                header.version_minor,
+            ));
+        }
+
+        let policy_len = header.policy_len as usize;
+        let payload_len = header.payload_len as usize;
+        let pad_len = header.pad_len as usize;
+        if header.header_len as usize != HEADER_LEN + policy_len {
+            return Err(CodecError::HeaderLenMismatch);
+        }
+        // The lengths are untrusted u32s: on 32-bit targets their sum can
+        // overflow usize, and such a frame cannot be data.len() long anyway
+        let total = (HEADER_LEN + policy_len)
+            .checked_add(payload_len)
+            .and_then(|len| len.checked_add(pad_len))
+            .ok_or(CodecError::PadLenMismatch)?;
+        if total != data.len() {
+            return Err(CodecError::PadLenMismatch);
+        }
+        let n = header.ss_n as usize;
+        if total != n * n * n {
+            return Err(CodecError::SizeMismatch);
+        }
+
+        let policy_end = HEADER_LEN + policy_len;
+        let payload_end = policy_end + payload_len;
+        if data[payload_end..].iter().any(|&b| b != 0) {
+            return Err(CodecError::NonZeroPadding);
+        }
+
+        Ok(Capsule {
+            header,
+            policy_core: data[HEADER_LEN..policy_end].to_vec(),
+            payload: data[policy_end..payload_end].to_vec(),
+            state: ExecState::default(),
+        })
     }
 
     // Helper: Verify Magic Bytes (BLE1)
@@ -22,6 +210,20 @@ impl LatticeCodec {
         if data.len() < 4 {
             return false;
         }
-        &data[0..4] == b"BLE1"
+        data[0..4] == MAGIC
     }
 }
+
+fn read_u16(data: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes([data[at], data[at + 1]])
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+fn read_hash(data: &[u8], at: usize) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&data[at..at + 32]);
+    hash
+}
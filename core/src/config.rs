@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+// How next_cycle orders the Active Queue (Spec v0.1 Section 2.5)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SchedulingMode {
+    // PRIORITY ascending (0 runs first), then x, y, z.
+    // Capsules sharing a CAPSULE_ID are de-duplicated: the first one in
+    // that order fires, later copies are discarded.
+    #[default]
+    Levin,
+    // Pre-spec ordering (z, then y, then x), priority ignored, no
+    // de-duplication. Kept so universes built on it replay unchanged.
+    Legacy,
+}
+
+// How STOREs from different capsules to the same payload byte in one cycle
+// are resolved (Spec v0.1 Section 2.8). Writers that agree on the value,
+// or a capsule overwriting its own earlier write, never conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WritePolicy {
+    // Halt the VM with VmError::WriteConflict.
+    #[default]
+    FailClosed,
+    // Bitwise OR of all written values (independent of execution order).
+    Merge,
+    // The capsule that ran first in the cycle keeps its value.
+    FirstWriter,
+}
+
+// Whether a cell may hold more than one capsule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Occupancy {
+    // One capsule per cell. Children and moves aimed at a taken cell follow
+    // the REPL and move policies; activate() refuses the capsule.
+    #[default]
+    Exclusive,
+    // Any number of capsules per cell; LOAD, STORE, SEND and ACTIVATE reach
+    // the first in queue order. How universes ran before occupancy rules.
+    Stacked,
+}
+
+// What happens to a REPL or SPAWN child aimed at a taken cell under
+// exclusive occupancy. Decided when the child is placed at the cycle
+// boundary; the parent's FLAG_BLOCKED says whether it was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ReplPolicy {
+    // No child; the parent traps with CellOccupied.
+    #[default]
+    Fail,
+    // The child takes the cell and the occupant leaves the lattice.
+    Overwrite,
+    // No child; the parent carries on.
+    Skip,
+}
+
+// What happens to a MOVE into a cell that is occupied when the move is
+// carried out at the cycle boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MovePolicy {
+    // The mover stays where it is.
+    #[default]
+    Block,
+    // The mover and the occupant trade cells, unless the occupant moved
+    // in this cycle; then the mover stays.
+    Swap,
+    // The mover stays and traps with MoveBlocked.
+    Trap,
+}
+
+// Runtime knobs of a LatticeVM. Saved with the universe.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VmConfig {
+    pub scheduling: SchedulingMode,
+    pub write_policy: WritePolicy,
+    // Treat capsules without a Policy Core as deny-all. When off they run
+    // unrestricted, which is how capsules built before Policy Cores behave.
+    pub require_policy_core: bool,
+    // Instructions a capsule may execute each time it fires. 1 is the old
+    // one-instruction-per-cycle model.
+    pub instruction_budget: u32,
+    // Instructions all capsules together may execute in one cycle; None is
+    // unlimited. Capsules later in the scheduling order are cut short first.
+    pub cycle_gas: Option<u64>,
+    pub move_policy: MovePolicy,
+    pub occupancy: Occupancy,
+    pub repl_policy: ReplPolicy,
+}
+
+impl VmConfig {
+    pub const DEFAULT_INSTRUCTION_BUDGET: u32 = 64;
+}
+
+impl Default for VmConfig {
+    fn default() -> Self {
+        Self {
+            scheduling: SchedulingMode::default(),
+            write_policy: WritePolicy::default(),
+            require_policy_core: false,
+            instruction_budget: Self::DEFAULT_INSTRUCTION_BUDGET,
+            cycle_gas: None,
+            move_policy: MovePolicy::default(),
+            occupancy: Occupancy::default(),
+            repl_policy: ReplPolicy::default(),
+        }
+    }
+}
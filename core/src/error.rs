@@ -0,0 +1,78 @@
+use crate::integrity::IntegrityError;
+use crate::lattice::Coord;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Errors reported by the VM. WriteConflict halts the lattice (fail-closed,
+// Spec v0.1 Section 2.8); Rejected only refuses the offending capsule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VmError {
+    // A capsule failed DECODE/VERIFY on activation and was not admitted
+    Rejected {
+        capsule_id: u32,
+        reason: IntegrityError,
+    },
+    // A user capsule used an id from the range reserved for host capsules
+    ReservedId {
+        capsule_id: u32,
+    },
+    // activate() aimed a capsule at a taken cell under exclusive occupancy
+    Occupied {
+        capsule_id: u32,
+        at: Coord,
+    },
+    // Two capsules wrote different values to the same payload byte in one cycle
+    WriteConflict {
+        target: Coord,
+        idx: usize,
+        first_writer: u32,
+        second_writer: u32,
+    },
+    // The id leaves no room above it for runtime ids (u32::MAX)
+    IdOutOfRange {
+        capsule_id: u32,
+    },
+    // A capsule with this id is already on the lattice
+    DuplicateId {
+        capsule_id: u32,
+    },
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VmError::Rejected { capsule_id, reason } => {
+                write!(f, "capsule {} rejected: {}", capsule_id, reason)
+            }
+            VmError::ReservedId { capsule_id } => {
+                write!(f, "capsule id {} is reserved", capsule_id)
+            }
+            VmError::Occupied {
+                capsule_id,
+                at: (x, y, z),
+            } => write!(
+                f,
+                "capsule {} cannot be placed at ({},{},{}): the cell is occupied",
+                capsule_id, x, y, z
+            ),
+            VmError::WriteConflict {
+                target: (x, y, z),
+                idx,
+                first_writer,
+                second_writer,
+            } => write!(
+                f,
+                "write conflict at ({},{},{})[{}]: capsules {} and {}",
+                x, y, z, idx, first_writer, second_writer
+            ),
+            VmError::IdOutOfRange { capsule_id } => {
+                write!(f, "capsule id {} is past the last usable id", capsule_id)
+            }
+            VmError::DuplicateId { capsule_id } => {
+                write!(f, "capsule id {} is already in use", capsule_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
@@ -0,0 +1,208 @@
+use crate::lattice::Coord;
+use crate::trap::Trap;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// What happened during a cycle, in execution order. The VM queues these in
+// LatticeVM::events; front ends drain the queue the same way they drain
+// output_buffer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VmEvent {
+    // SPAWN created a new capsule
+    Spawned {
+        parent: u32,
+        child: u32,
+        at: Coord,
+    },
+    // REPL cloned its capsule
+    Replicated {
+        parent: u32,
+        child: u32,
+        at: Coord,
+    },
+    // Executed VOID and left the lattice at the end of the cycle
+    Voided {
+        capsule_id: u32,
+        at: Coord,
+    },
+    // Another capsule with the same id was scheduled first; this copy was
+    // removed from the lattice before the cycle ran
+    Duplicate {
+        capsule_id: u32,
+        at: Coord,
+    },
+    // The Policy Core's deadline passed; the capsule halted instead of firing
+    Expired {
+        capsule_id: u32,
+        deadline: u64,
+    },
+    // LOG
+    Logged {
+        capsule_id: u32,
+        value: i32,
+    },
+    // A STORE reached the cycle boundary. Not applied: no capsule at the
+    // target, or the byte lies beyond its frame (the writer traps).
+    Wrote {
+        writer: u32,
+        target: Coord,
+        idx: usize,
+        value: u8,
+        applied: bool,
+    },
+    // An instruction trapped at `ip`. Handled: the capsule went on in its
+    // fault handler; otherwise it is now Faulted.
+    Trapped {
+        capsule_id: u32,
+        ip: u32,
+        trap: Trap,
+        handled: bool,
+    },
+    // A SEND reached the cycle boundary. Not delivered: no capsule at the
+    // target, or its mailbox was full.
+    Sent {
+        from: u32,
+        target: Coord,
+        value: i32,
+        delivered: bool,
+    },
+    // A REPL or SPAWN child was not placed: its cell was taken
+    BirthBlocked {
+        parent: u32,
+        child: u32,
+        at: Coord,
+    },
+    // MOVE relocated a capsule, or a swap displaced it
+    Moved {
+        capsule_id: u32,
+        from: Coord,
+        to: Coord,
+    },
+    // A MOVE into an occupied cell left the capsule where it was
+    MoveBlocked {
+        capsule_id: u32,
+        at: Coord,
+        target: Coord,
+    },
+    CycleCompleted {
+        cycle: u64,
+        population: usize,
+    },
+    // The VM was moved back in time
+    Rewound {
+        from: u64,
+        to: u64,
+    },
+}
+
+impl fmt::Display for VmEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VmEvent::Spawned { parent, child, at } => write!(
+                f,
+                "VM [SPAWN]: Capsule {} spawned {} at ({},{},{})",
+                parent, child, at.0, at.1, at.2
+            ),
+            VmEvent::Replicated { parent, child, at } => write!(
+                f,
+                "VM [REPL]: Capsule {} replicated to {} at ({},{},{})",
+                parent, child, at.0, at.1, at.2
+            ),
+            VmEvent::Voided { capsule_id, at } => write!(
+                f,
+                "VM [VOID]: Capsule {} left ({},{},{})",
+                capsule_id, at.0, at.1, at.2
+            ),
+            VmEvent::Duplicate { capsule_id, at } => write!(
+                f,
+                "VM [SCHED]: Duplicate of capsule {} removed from ({},{},{})",
+                capsule_id, at.0, at.1, at.2
+            ),
+            VmEvent::Expired {
+                capsule_id,
+                deadline,
+            } => write!(
+                f,
+                "VM [POLICY]: Capsule {} halted, its deadline was cycle {}",
+                capsule_id, deadline
+            ),
+            VmEvent::Logged { capsule_id, value } => {
+                write!(f, "VM [LOG]: Capsule {} R0 = {}", capsule_id, value)
+            }
+            VmEvent::Wrote {
+                writer,
+                target,
+                idx,
+                value,
+                applied,
+            } => write!(
+                f,
+                "VM [STORE]: Capsule {} wrote {} to ({},{},{})[{}]{}",
+                writer,
+                value,
+                target.0,
+                target.1,
+                target.2,
+                idx,
+                if *applied { "" } else { " (dropped)" }
+            ),
+            VmEvent::Trapped {
+                capsule_id,
+                ip,
+                trap,
+                handled,
+            } => write!(
+                f,
+                "VM [TRAP]: Capsule {} at ip {}: {}{}",
+                capsule_id,
+                ip,
+                trap,
+                if *handled { " (handled)" } else { "" }
+            ),
+            VmEvent::Sent {
+                from,
+                target,
+                value,
+                delivered,
+            } => write!(
+                f,
+                "VM [SEND]: Capsule {} sent {} to ({},{},{}){}",
+                from,
+                value,
+                target.0,
+                target.1,
+                target.2,
+                if *delivered { "" } else { " (dropped)" }
+            ),
+            VmEvent::BirthBlocked { parent, child, at } => write!(
+                f,
+                "VM [REPL]: Capsule {} could not place {} at ({},{},{}), the cell is occupied",
+                parent, child, at.0, at.1, at.2
+            ),
+            VmEvent::Moved {
+                capsule_id,
+                from,
+                to,
+            } => write!(
+                f,
+                "VM [MOVE]: Capsule {} moved from ({},{},{}) to ({},{},{})",
+                capsule_id, from.0, from.1, from.2, to.0, to.1, to.2
+            ),
+            VmEvent::MoveBlocked {
+                capsule_id,
+                at,
+                target,
+            } => write!(
+                f,
+                "VM [MOVE]: Capsule {} at ({},{},{}) is blocked from ({},{},{})",
+                capsule_id, at.0, at.1, at.2, target.0, target.1, target.2
+            ),
+            VmEvent::CycleCompleted { cycle, population } => {
+                write!(f, "VM [CYCLE]: {} complete, {} capsules", cycle, population)
+            }
+            VmEvent::Rewound { from, to } => {
+                write!(f, "VM [REWIND]: cycle {} -> {}", from, to)
+            }
+        }
+    }
+}
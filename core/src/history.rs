@@ -0,0 +1,182 @@
+use crate::events::VmEvent;
+use crate::trace::Injection;
+use crate::vm::LatticeVM;
+use std::collections::VecDeque;
+use std::fmt;
+
+// Time travel: the VM keeps a bounded ring of snapshots taken every
+// `interval` cycles, plus a journal of the capsules admitted since the oldest
+// one. Any cycle from the oldest snapshot to now is rebuilt by cloning the
+// nearest earlier snapshot and re-running the journal on top of it, which is
+// exact because cycles are deterministic.
+#[derive(Clone)]
+pub struct History {
+    pub interval: u64,
+    pub capacity: usize,
+    snapshots: VecDeque<LatticeVM>,
+    journal: Vec<Injection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryError {
+    Disabled,
+    // Older than the oldest snapshot still kept
+    Unavailable { cycle: u64, oldest: u64 },
+    // Past cycles only; use seek() to run forward
+    InFuture { cycle: u64, current: u64 },
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryError::Disabled => write!(f, "history is not enabled"),
+            HistoryError::Unavailable { cycle, oldest } => write!(
+                f,
+                "cycle {} is no longer kept (oldest is {})",
+                cycle, oldest
+            ),
+            HistoryError::InFuture { cycle, current } => {
+                write!(f, "cycle {} is after the current cycle {}", cycle, current)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+impl History {
+    // Cycles covered by the kept snapshots (oldest, newest)
+    pub fn range(&self) -> Option<(u64, u64)> {
+        Some((
+            self.snapshots.front()?.cycle_count,
+            self.snapshots.back()?.cycle_count,
+        ))
+    }
+}
+
+impl LatticeVM {
+    // Starts keeping a snapshot every `interval` cycles, at most `capacity`
+    // of them. Replaces any history kept so far.
+    pub fn enable_history(&mut self, interval: u64, capacity: usize) {
+        let mut history = History {
+            interval: interval.max(1),
+            capacity: capacity.max(1),
+            snapshots: VecDeque::new(),
+            journal: Vec::new(),
+        };
+        history.snapshots.push_back(self.detached_clone());
+        self.history = Some(history);
+    }
+
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    pub fn history(&self) -> Option<&History> {
+        self.history.as_ref()
+    }
+
+    // Called from admit()
+    pub(crate) fn journal_injection(&mut self, injection: &Injection) {
+        if let Some(history) = &mut self.history {
+            history.journal.push(injection.clone());
+        }
+    }
+
+    // Called at the end of every cycle
+    pub(crate) fn record_history(&mut self) {
+        let due = match &self.history {
+            Some(history) => self.cycle_count.is_multiple_of(history.interval),
+            None => false,
+        };
+        if !due {
+            return;
+        }
+        let snapshot = self.detached_clone();
+        if let Some(history) = &mut self.history {
+            history.snapshots.push_back(snapshot);
+            if history.snapshots.len() > history.capacity {
+                history.snapshots.pop_front();
+                let oldest = history.snapshots[0].cycle_count;
+                history.journal.retain(|i| i.cycle >= oldest);
+            }
+        }
+    }
+
+    // The VM as it was right after `cycle` ran. Does not modify self.
+    pub fn state_at(&self, cycle: u64) -> Result<LatticeVM, HistoryError> {
+        let history = self.history.as_ref().ok_or(HistoryError::Disabled)?;
+        if cycle > self.cycle_count {
+            return Err(HistoryError::InFuture {
+                cycle,
+                current: self.cycle_count,
+            });
+        }
+        let base = history
+            .snapshots
+            .iter()
+            .rev()
+            .find(|s| s.cycle_count <= cycle)
+            .ok_or(HistoryError::Unavailable {
+                cycle,
+                oldest: history.range().map_or(self.cycle_count, |r| r.0),
+            })?;
+
+        let mut vm = base.clone();
+        let mut journal = history
+            .journal
+            .iter()
+            .filter(|i| i.cycle >= base.cycle_count)
+            .peekable();
+        while vm.cycle_count < cycle {
+            while let Some(inj) = journal.next_if(|i| i.cycle == vm.cycle_count) {
+                let _ = vm.activate_with_role(inj.capsule.clone(), inj.role);
+            }
+            if vm.next_cycle().is_err() {
+                break;
+            }
+        }
+        Ok(vm)
+    }
+
+    // Moves this VM back to `cycle`. Later snapshots and journal entries are
+    // discarded; a trace being recorded is stopped.
+    pub fn rewind_to(&mut self, cycle: u64) -> Result<(), HistoryError> {
+        let mut past = self.state_at(cycle)?;
+        let mut history = self.history.take().ok_or(HistoryError::Disabled)?;
+        history.snapshots.retain(|s| s.cycle_count <= cycle);
+        history.journal.retain(|i| i.cycle < cycle);
+        past.history = Some(history);
+        past.events = vec![VmEvent::Rewound {
+            from: self.cycle_count,
+            to: past.cycle_count,
+        }];
+        *self = past;
+        Ok(())
+    }
+
+    pub fn step_back(&mut self) -> Result<(), HistoryError> {
+        self.rewind_to(self.cycle_count.saturating_sub(1))
+    }
+
+    // Rewinds to a past cycle, or runs forward to a later one (stopping
+    // early if the VM halts or empties).
+    pub fn seek(&mut self, cycle: u64) -> Result<(), HistoryError> {
+        if cycle < self.cycle_count {
+            return self.rewind_to(cycle);
+        }
+        while self.cycle_count < cycle && !self.is_void() {
+            if self.next_cycle().is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // A new, independent universe starting from the state after `cycle`.
+    pub fn fork_at(&self, cycle: u64, universe_id: String) -> Result<LatticeVM, HistoryError> {
+        let mut fork = self.state_at(cycle)?;
+        fork.universe_id = universe_id;
+        Ok(fork)
+    }
+}
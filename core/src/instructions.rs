@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
+#[allow(non_camel_case_types)] // Variants are spelled like their BASM mnemonics
 pub enum OpCode {
     NOOP = 0,
     HALT = 1,
@@ -16,10 +17,276 @@ pub enum OpCode {
     JMP = 11,
     BEQ = 12,
     REPL = 13,
-    VOID = 14, // NEW: Suicide / Delete Self
+    VOID = 14,              // NEW: Suicide / Delete Self
+    GET = 15,               // R0 = Rn
+    PUT = 16,               // Rn = R0
+    WAIT = 17,              // Sleep for n cycles
+    ACTIVATE = 18,          // Wake the capsule at absolute (x, y, z)
+    ACTIVATE_NEIGHBOR = 19, // Wake the capsule at relative (dx, dy, dz)
+    DORMANT = 20,           // Go back to sleep until activated
+    YIELD = 21,             // End this activation, resume here next cycle
+    ONFAULT = 22,           // Arm the fault handler at the given offset
+    SEND = 23,              // R0 to the mailbox at relative (dx, dy, dz)
+    RECV = 24,              // Take a message, blocking while none has arrived
+    POLL = 25,              // R0 = messages waiting
+    CALL = 26,              // Push the return address, jump to the given offset
+    RET = 27,               // Pop the return address and jump back
+    MOV = 28,               // dst = src
+    LDI = 29,               // reg = signed immediate byte
+    MUL = 30,               // dst = dst * src
+    DIV = 31,               // dst = dst / src, truncated
+    MOD = 32,               // dst = dst % src, sign of dst
+    AND = 33,               // dst = dst & src
+    OR = 34,                // dst = dst | src
+    XOR = 35,               // dst = dst ^ src
+    SHL = 36,               // dst = dst << (src mod 32)
+    SHR = 37,               // dst = dst >> (src mod 32), arithmetic
+    CMP = 38,               // Flags from comparing a with b (signed)
+    BNE = 39,               // Branch if R0 != val
+    BLT = 40,               // Branch if R0 < val
+    BGT = 41,               // Branch if R0 > val
+    BLE = 42,               // Branch if R0 <= val
+    BGE = 43,               // Branch if R0 >= val
+    BEQF = 44,              // Branch if the last CMP found a == b
+    BNEF = 45,              // ... a != b
+    BLTF = 46,              // ... a < b
+    BGTF = 47,              // ... a > b
+    BLEF = 48,              // ... a <= b
+    BGEF = 49,              // ... a >= b
+    GETX = 50,              // R0 = own x
+    GETY = 51,              // R0 = own y
+    GETZ = 52,              // R0 = own z
+    GETID = 53,             // R0 = own CAPSULE_ID
+    GETCYC = 54,            // R0 = current cycle
+    SENSE = 55,             // R0 = 1 if (dx, dy, dz) is occupied
+    COUNTN = 56,            // R0 = occupied neighbour cells
+    MOVE = 57,              // Relocate to relative (dx, dy, dz), keeping id and state
+    BBLK = 58,              // Branch if the last REPL, SPAWN or MOVE was blocked
 }
 
 impl OpCode {
+    // The complete opcode table, in byte order. DICT_HASH is computed over it.
+    pub const ALL: [OpCode; 58] = [
+        OpCode::NOOP,
+        OpCode::HALT,
+        OpCode::ADD,
+        OpCode::SUB,
+        OpCode::INC,
+        OpCode::DEC,
+        OpCode::LOG,
+        OpCode::SPAWN,
+        OpCode::STORE,
+        OpCode::LOAD,
+        OpCode::JMP,
+        OpCode::BEQ,
+        OpCode::REPL,
+        OpCode::VOID,
+        OpCode::GET,
+        OpCode::PUT,
+        OpCode::WAIT,
+        OpCode::ACTIVATE,
+        OpCode::ACTIVATE_NEIGHBOR,
+        OpCode::DORMANT,
+        OpCode::YIELD,
+        OpCode::ONFAULT,
+        OpCode::SEND,
+        OpCode::RECV,
+        OpCode::POLL,
+        OpCode::CALL,
+        OpCode::RET,
+        OpCode::MOV,
+        OpCode::LDI,
+        OpCode::MUL,
+        OpCode::DIV,
+        OpCode::MOD,
+        OpCode::AND,
+        OpCode::OR,
+        OpCode::XOR,
+        OpCode::SHL,
+        OpCode::SHR,
+        OpCode::CMP,
+        OpCode::BNE,
+        OpCode::BLT,
+        OpCode::BGT,
+        OpCode::BLE,
+        OpCode::BGE,
+        OpCode::BEQF,
+        OpCode::BNEF,
+        OpCode::BLTF,
+        OpCode::BGTF,
+        OpCode::BLEF,
+        OpCode::BGEF,
+        OpCode::GETX,
+        OpCode::GETY,
+        OpCode::GETZ,
+        OpCode::GETID,
+        OpCode::GETCYC,
+        OpCode::SENSE,
+        OpCode::COUNTN,
+        OpCode::MOVE,
+        OpCode::BBLK,
+    ];
+
+    // BASM spelling of the opcode
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            OpCode::NOOP => "NOOP",
+            OpCode::HALT => "HALT",
+            OpCode::ADD => "ADD",
+            OpCode::SUB => "SUB",
+            OpCode::INC => "INC",
+            OpCode::DEC => "DEC",
+            OpCode::LOG => "LOG",
+            OpCode::SPAWN => "SPAWN",
+            OpCode::STORE => "STORE",
+            OpCode::LOAD => "LOAD",
+            OpCode::JMP => "JMP",
+            OpCode::BEQ => "BEQ",
+            OpCode::REPL => "REPL",
+            OpCode::VOID => "VOID",
+            OpCode::GET => "GET",
+            OpCode::PUT => "PUT",
+            OpCode::WAIT => "WAIT",
+            OpCode::ACTIVATE => "ACTIVATE",
+            OpCode::ACTIVATE_NEIGHBOR => "ACTIVATE_NEIGHBOR",
+            OpCode::DORMANT => "DORMANT",
+            OpCode::YIELD => "YIELD",
+            OpCode::ONFAULT => "ONFAULT",
+            OpCode::SEND => "SEND",
+            OpCode::RECV => "RECV",
+            OpCode::POLL => "POLL",
+            OpCode::CALL => "CALL",
+            OpCode::RET => "RET",
+            OpCode::MOV => "MOV",
+            OpCode::LDI => "LDI",
+            OpCode::MUL => "MUL",
+            OpCode::DIV => "DIV",
+            OpCode::MOD => "MOD",
+            OpCode::AND => "AND",
+            OpCode::OR => "OR",
+            OpCode::XOR => "XOR",
+            OpCode::SHL => "SHL",
+            OpCode::SHR => "SHR",
+            OpCode::CMP => "CMP",
+            OpCode::BNE => "BNE",
+            OpCode::BLT => "BLT",
+            OpCode::BGT => "BGT",
+            OpCode::BLE => "BLE",
+            OpCode::BGE => "BGE",
+            OpCode::BEQF => "BEQF",
+            OpCode::BNEF => "BNEF",
+            OpCode::BLTF => "BLTF",
+            OpCode::BGTF => "BGTF",
+            OpCode::BLEF => "BLEF",
+            OpCode::BGEF => "BGEF",
+            OpCode::GETX => "GETX",
+            OpCode::GETY => "GETY",
+            OpCode::GETZ => "GETZ",
+            OpCode::GETID => "GETID",
+            OpCode::GETCYC => "GETCYC",
+            OpCode::SENSE => "SENSE",
+            OpCode::COUNTN => "COUNTN",
+            OpCode::MOVE => "MOVE",
+            OpCode::BBLK => "BBLK",
+        }
+    }
+
+    // Whether the VM reads operand `i` as signed: offsets, coordinates,
+    // LDI immediates and the R0 branch literals
+    pub fn operand_signed(self, i: usize) -> bool {
+        match self {
+            OpCode::REPL
+            | OpCode::ACTIVATE
+            | OpCode::ACTIVATE_NEIGHBOR
+            | OpCode::SEND
+            | OpCode::SENSE
+            | OpCode::MOVE => true,
+            OpCode::STORE | OpCode::LOAD | OpCode::SPAWN => i < 3,
+            OpCode::BEQ | OpCode::BNE | OpCode::BLT | OpCode::BGT | OpCode::BLE | OpCode::BGE => {
+                i == 0
+            }
+            OpCode::LDI => i == 1,
+            _ => false,
+        }
+    }
+
+    // Whether operand `i` is a big-endian 16-bit word rather than a byte.
+    // ACTIVATE's absolute coordinates span the whole i16 lattice.
+    pub fn operand_wide(self, _i: usize) -> bool {
+        self == OpCode::ACTIVATE
+    }
+
+    // Operands that follow the opcode; a wide one takes two bytes
+    pub fn operand_count(self) -> usize {
+        match self {
+            OpCode::ACTIVATE => 3,
+            op => op.operand_len(),
+        }
+    }
+
+    // Operand bytes that follow the opcode
+    pub fn operand_len(self) -> usize {
+        match self {
+            OpCode::GET
+            | OpCode::PUT
+            | OpCode::JMP
+            | OpCode::WAIT
+            | OpCode::ONFAULT
+            | OpCode::CALL
+            | OpCode::BEQF
+            | OpCode::BNEF
+            | OpCode::BLTF
+            | OpCode::BGTF
+            | OpCode::BLEF
+            | OpCode::BGEF
+            | OpCode::COUNTN
+            | OpCode::BBLK => 1,
+            OpCode::BEQ
+            | OpCode::MOV
+            | OpCode::LDI
+            | OpCode::MUL
+            | OpCode::DIV
+            | OpCode::MOD
+            | OpCode::AND
+            | OpCode::OR
+            | OpCode::XOR
+            | OpCode::SHL
+            | OpCode::SHR
+            | OpCode::CMP
+            | OpCode::BNE
+            | OpCode::BLT
+            | OpCode::BGT
+            | OpCode::BLE
+            | OpCode::BGE => 2,
+            OpCode::REPL
+            | OpCode::ACTIVATE_NEIGHBOR
+            | OpCode::SEND
+            | OpCode::SENSE
+            | OpCode::MOVE => 3,
+            OpCode::STORE | OpCode::LOAD => 4,
+            OpCode::SPAWN => 5,
+            OpCode::ACTIVATE => 6,
+            OpCode::NOOP
+            | OpCode::HALT
+            | OpCode::ADD
+            | OpCode::SUB
+            | OpCode::INC
+            | OpCode::DEC
+            | OpCode::LOG
+            | OpCode::VOID
+            | OpCode::DORMANT
+            | OpCode::YIELD
+            | OpCode::RECV
+            | OpCode::POLL
+            | OpCode::RET
+            | OpCode::GETX
+            | OpCode::GETY
+            | OpCode::GETZ
+            | OpCode::GETID
+            | OpCode::GETCYC => 0,
+        }
+    }
+
     pub fn from_u8(v: u8) -> Option<Self> {
         match v {
             0 => Some(OpCode::NOOP),
@@ -36,6 +303,50 @@ impl OpCode {
             12 => Some(OpCode::BEQ),
             13 => Some(OpCode::REPL),
             14 => Some(OpCode::VOID),
+            15 => Some(OpCode::GET),
+            16 => Some(OpCode::PUT),
+            17 => Some(OpCode::WAIT),
+            18 => Some(OpCode::ACTIVATE),
+            19 => Some(OpCode::ACTIVATE_NEIGHBOR),
+            20 => Some(OpCode::DORMANT),
+            21 => Some(OpCode::YIELD),
+            22 => Some(OpCode::ONFAULT),
+            23 => Some(OpCode::SEND),
+            24 => Some(OpCode::RECV),
+            25 => Some(OpCode::POLL),
+            26 => Some(OpCode::CALL),
+            27 => Some(OpCode::RET),
+            28 => Some(OpCode::MOV),
+            29 => Some(OpCode::LDI),
+            30 => Some(OpCode::MUL),
+            31 => Some(OpCode::DIV),
+            32 => Some(OpCode::MOD),
+            33 => Some(OpCode::AND),
+            34 => Some(OpCode::OR),
+            35 => Some(OpCode::XOR),
+            36 => Some(OpCode::SHL),
+            37 => Some(OpCode::SHR),
+            38 => Some(OpCode::CMP),
+            39 => Some(OpCode::BNE),
+            40 => Some(OpCode::BLT),
+            41 => Some(OpCode::BGT),
+            42 => Some(OpCode::BLE),
+            43 => Some(OpCode::BGE),
+            44 => Some(OpCode::BEQF),
+            45 => Some(OpCode::BNEF),
+            46 => Some(OpCode::BLTF),
+            47 => Some(OpCode::BGTF),
+            48 => Some(OpCode::BLEF),
+            49 => Some(OpCode::BGEF),
+            50 => Some(OpCode::GETX),
+            51 => Some(OpCode::GETY),
+            52 => Some(OpCode::GETZ),
+            53 => Some(OpCode::GETID),
+            54 => Some(OpCode::GETCYC),
+            55 => Some(OpCode::SENSE),
+            56 => Some(OpCode::COUNTN),
+            57 => Some(OpCode::MOVE),
+            58 => Some(OpCode::BBLK),
             _ => None,
         }
     }
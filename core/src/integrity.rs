@@ -0,0 +1,107 @@
+use crate::capsules::Capsule;
+use crate::codec::{CodecError, LatticeCodec, OFF_CAPSULE_HASH};
+use crate::instructions::OpCode;
+use crate::policy::{PolicyCore, PolicyError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+// Capsule Integrity (Spec v0.1 Section 8, Encoding Section 3.2)
+//
+// The three header hashes are SHA-256 digests:
+//   DICT_HASH        - the opcode table the payload was compiled against
+//   POLICY_CORE_HASH - the canonical Policy Core bytes (Q0-Q2)
+//   CAPSULE_HASH     - the full BLE frame, with the CAPSULE_HASH field zeroed
+//
+// Hashes describe a capsule as it was admitted, or as it was born: REPL and
+// SPAWN children are sealed when they are placed. Changes the VM itself makes
+// later (STORE into the payload, MOVE) are not re-sealed.
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IntegrityError {
+    Codec(CodecError),
+    // Length fields in the header do not describe the capsule contents
+    StaleLengths,
+    // Policy Core bytes are malformed or not canonical
+    Policy(PolicyError),
+    DictHashMismatch,
+    PolicyCoreHashMismatch,
+    CapsuleHashMismatch,
+}
+
+impl fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityError::Codec(e) => write!(f, "decode failed: {}", e),
+            IntegrityError::StaleLengths => write!(f, "header lengths do not match contents"),
+            IntegrityError::Policy(e) => write!(f, "policy core rejected: {}", e),
+            IntegrityError::DictHashMismatch => write!(f, "DICT_HASH mismatch"),
+            IntegrityError::PolicyCoreHashMismatch => write!(f, "POLICY_CORE_HASH mismatch"),
+            IntegrityError::CapsuleHashMismatch => write!(f, "CAPSULE_HASH mismatch"),
+        }
+    }
+}
+
+impl std::error::Error for IntegrityError {}
+
+impl From<CodecError> for IntegrityError {
+    fn from(e: CodecError) -> Self {
+        IntegrityError::Codec(e)
+    }
+}
+
+// SHA-256 over "<byte> <MNEMONIC>\n" for every opcode, in byte order
+pub fn dict_hash() -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for op in OpCode::ALL {
+        hasher.update(format!("{} {}\n", op as u8, op.mnemonic()).as_bytes());
+    }
+    hasher.finalize().into()
+}
+
+// Callers hash the canonical encoding (PolicyCore::encode); verify() rejects
+// anything else.
+pub fn policy_core_hash(policy_core: &[u8]) -> [u8; 32] {
+    Sha256::digest(policy_core).into()
+}
+
+pub fn capsule_hash(capsule: &Capsule) -> Result<[u8; 32], CodecError> {
+    let mut frame = LatticeCodec::encode(capsule)?;
+    frame[OFF_CAPSULE_HASH..OFF_CAPSULE_HASH + 32].fill(0);
+    Ok(Sha256::digest(&frame).into())
+}
+
+// Fills in the length fields and all three hashes.
+pub fn seal(capsule: &mut Capsule) -> Result<(), CodecError> {
+    LatticeCodec::fill_lengths(capsule)?;
+    capsule.header.dict_hash = dict_hash();
+    capsule.header.policy_core_hash = policy_core_hash(&capsule.policy_core);
+    capsule.header.capsule_hash = capsule_hash(capsule)?;
+    Ok(())
+}
+
+// The VERIFY step of the execution ceremony (Levin Section 2.6).
+pub fn verify(capsule: &Capsule) -> Result<(), IntegrityError> {
+    let mut expected = capsule.clone();
+    LatticeCodec::fill_lengths(&mut expected)?;
+    if expected.header.header_len != capsule.header.header_len
+        || expected.header.policy_len != capsule.header.policy_len
+        || expected.header.payload_len != capsule.header.payload_len
+        || expected.header.pad_len != capsule.header.pad_len
+    {
+        return Err(IntegrityError::StaleLengths);
+    }
+    if !capsule.policy_core.is_empty() {
+        PolicyCore::decode_canonical(&capsule.policy_core).map_err(IntegrityError::Policy)?;
+    }
+    if capsule.header.dict_hash != dict_hash() {
+        return Err(IntegrityError::DictHashMismatch);
+    }
+    if capsule.header.policy_core_hash != policy_core_hash(&capsule.policy_core) {
+        return Err(IntegrityError::PolicyCoreHashMismatch);
+    }
+    if capsule.header.capsule_hash != capsule_hash(capsule)? {
+        return Err(IntegrityError::CapsuleHashMismatch);
+    }
+    Ok(())
+}
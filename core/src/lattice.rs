@@ -0,0 +1,48 @@
+use crate::capsules::Capsule;
+use std::collections::HashMap;
+
+// A cell of the 3D lattice (x, y, z)
+pub type Coord = (i16, i16, i16);
+
+// Coordinate -> queue slot lookup, so LOAD and the write flush do not have to
+// scan the whole queue. If several capsules share a cell, the first one in
+// queue order is the one indexed (the same capsule a linear scan would find).
+#[derive(Debug, Clone, Default)]
+pub struct SpatialIndex {
+    cells: HashMap<Coord, usize>,
+}
+
+impl SpatialIndex {
+    pub fn build(queue: &[Capsule]) -> Self {
+        let mut index = Self::default();
+        index.rebuild(queue);
+        index
+    }
+
+    pub fn rebuild(&mut self, queue: &[Capsule]) {
+        self.cells.clear();
+        for (slot, capsule) in queue.iter().enumerate() {
+            self.insert(capsule.coord(), slot);
+        }
+    }
+
+    pub fn insert(&mut self, coord: Coord, slot: usize) {
+        self.cells.entry(coord).or_insert(slot);
+    }
+
+    pub fn get(&self, coord: Coord) -> Option<usize> {
+        self.cells.get(&coord).copied()
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+}
@@ -1,5 +1,17 @@
+pub mod asm;
 pub mod capsules;
+pub mod codec;
+pub mod config;
+pub mod error;
+pub mod events;
+pub mod history;
 pub mod instructions;
+pub mod integrity;
+pub mod lattice;
+pub mod policy;
+pub mod scenario;
+pub mod trace;
+pub mod trap;
 pub mod vm;
 
 // --- HEAVY MODULES (CLI ONLY) ---
@@ -7,10 +19,10 @@ pub mod vm;
 // We hide them from WASM to prevent crashes.
 
 #[cfg(feature = "cli-mode")]
-pub mod codec;
+pub mod net;
 
 #[cfg(feature = "cli-mode")]
-pub mod net;
+pub mod world;
 
 pub fn version() -> &'static str {
     "0.1.0"
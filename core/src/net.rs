@@ -1,4 +1,3 @@
-use crate::capsules::Capsule;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -13,8 +12,8 @@ pub enum NetMessage {
     // 2. The Handshake (Response)
     Welcome { server_version: String },
 
-    // 3. The Payload (Work)
-    InjectCapsule(Capsule),
+    // 3. The Payload (Work): a BLE-encoded capsule (see codec::LatticeCodec)
+    InjectCapsule(Vec<u8>),
 }
 
 // --- NETWORK I/O HELPERS ---
@@ -0,0 +1,349 @@
+use crate::lattice::Coord;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Levin Policy Core v0.1 (Spec Section 1)
+//
+// Q0 CORE_INTENT      - opaque bytes, only canonicalized and hashed
+// Q1 SCOPE_FILTER     - ALLOW/DENY rules over operations, targets and data
+//                       classes. DENY_BY_DEFAULT, LAST_MATCH_WINS.
+// Q2 HARD_CONSTRAINTS - limits that hold no matter what Q1 allows
+//
+// Canonical encoding (big-endian):
+//   u16 intent_len | intent
+//   u16 rule_count | rule*    rule = effect u8 | operations u16 | data u8
+//                                    | target tag u8 [| min xyz, max xyz as i16]
+//   u16 constraint_count | constraint*    constraint = tag u8 | value u64
+// Rules keep their order (it carries meaning). Constraints are sorted by
+// tag then value with duplicates removed.
+
+// Operation classes a rule can name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Operation {
+    Compute,   // Register arithmetic and control flow
+    Read,      // LOAD
+    Write,     // STORE
+    Replicate, // REPL
+    Spawn,     // SPAWN
+    Void,      // VOID
+    Emit,      // LOG and oracle output
+    Activate,  // ACTIVATE / ACTIVATE_NEIGHBOR
+    Message,   // SEND
+    Move,      // MOVE
+}
+
+impl Operation {
+    pub const ALL_BITS: u16 = 0x3FF;
+
+    pub fn bit(self) -> u16 {
+        1 << (self as u16)
+    }
+}
+
+// Data classes an operation touches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DataClass {
+    Code,      // Payload bytes
+    Registers, // R0-R3
+    Output,    // Anything leaving the lattice
+}
+
+impl DataClass {
+    pub const ALL_BITS: u8 = 0x07;
+
+    pub fn bit(self) -> u8 {
+        1 << (self as u8)
+    }
+}
+
+// Which cells a rule applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Target {
+    Any,
+    SelfCell,                          // The capsule's own cell
+    Region { min: Coord, max: Coord }, // Inclusive absolute bounding box
+}
+
+impl Target {
+    fn matches(&self, own: Coord, target: Coord) -> bool {
+        match *self {
+            Target::Any => true,
+            Target::SelfCell => own == target,
+            Target::Region { min, max } => {
+                (min.0..=max.0).contains(&target.0)
+                    && (min.1..=max.1).contains(&target.1)
+                    && (min.2..=max.2).contains(&target.2)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Effect {
+    Deny = 0,
+    Allow = 1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScopeRule {
+    pub effect: Effect,
+    pub operations: u16, // Operation bits
+    pub data: u8,        // DataClass bits
+    pub target: Target,
+}
+
+impl ScopeRule {
+    pub fn allow(operations: u16, target: Target) -> Self {
+        Self {
+            effect: Effect::Allow,
+            operations,
+            data: DataClass::ALL_BITS,
+            target,
+        }
+    }
+
+    pub fn deny(operations: u16, target: Target) -> Self {
+        Self {
+            effect: Effect::Deny,
+            operations,
+            data: DataClass::ALL_BITS,
+            target,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum HardConstraint {
+    // REPL + SPAWN a single capsule may perform over its lifetime
+    MaxReplications(u64),
+    // Last cycle the capsule may execute in; it halts afterwards
+    Deadline(u64),
+}
+
+impl HardConstraint {
+    fn tag(&self) -> u8 {
+        match self {
+            HardConstraint::MaxReplications(_) => 1,
+            HardConstraint::Deadline(_) => 2,
+        }
+    }
+
+    fn value(&self) -> u64 {
+        match *self {
+            HardConstraint::MaxReplications(v) | HardConstraint::Deadline(v) => v,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PolicyError {
+    Truncated,
+    TrailingBytes,
+    UnknownTag(u8),
+    UnknownBits,
+    // Decodes, but is not in canonical form
+    NonCanonical,
+    // Intent, rule list or constraint list too long for its u16 count
+    TooLong,
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::Truncated => write!(f, "policy core is truncated"),
+            PolicyError::TrailingBytes => write!(f, "trailing bytes after policy core"),
+            PolicyError::UnknownTag(t) => write!(f, "unknown policy tag {}", t),
+            PolicyError::UnknownBits => write!(f, "undefined operation or data bits"),
+            PolicyError::NonCanonical => write!(f, "policy core is not canonical"),
+            PolicyError::TooLong => write!(f, "policy core field exceeds 65535 entries"),
+        }
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyCore {
+    pub intent: Vec<u8>,                  // Q0
+    pub scope: Vec<ScopeRule>,            // Q1
+    pub constraints: Vec<HardConstraint>, // Q2
+}
+
+impl PolicyCore {
+    // A core whose SCOPE_FILTER allows everything
+    pub fn allow_all(intent: &[u8]) -> Self {
+        Self {
+            intent: intent.to_vec(),
+            scope: vec![ScopeRule::allow(Operation::ALL_BITS, Target::Any)],
+            constraints: Vec::new(),
+        }
+    }
+
+    // SCOPE_FILTER decision for one operation (DENY_BY_DEFAULT, LAST_MATCH_WINS)
+    pub fn permits(&self, op: Operation, data: DataClass, own: Coord, target: Coord) -> bool {
+        self.scope
+            .iter()
+            .rev()
+            .find(|rule| {
+                rule.operations & op.bit() != 0
+                    && rule.data & data.bit() != 0
+                    && rule.target.matches(own, target)
+            })
+            .is_some_and(|rule| rule.effect == Effect::Allow)
+    }
+
+    pub fn max_replications(&self) -> Option<u64> {
+        self.constraints.iter().find_map(|c| match c {
+            HardConstraint::MaxReplications(n) => Some(*n),
+            _ => None,
+        })
+    }
+
+    pub fn deadline(&self) -> Option<u64> {
+        self.constraints.iter().find_map(|c| match c {
+            HardConstraint::Deadline(n) => Some(*n),
+            _ => None,
+        })
+    }
+
+    // Canonical Q0-Q2 bytes, as stored in Capsule::policy_core
+    pub fn encode(&self) -> Result<Vec<u8>, PolicyError> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&count(self.intent.len())?.to_be_bytes());
+        out.extend_from_slice(&self.intent);
+
+        out.extend_from_slice(&count(self.scope.len())?.to_be_bytes());
+        for rule in &self.scope {
+            out.push(rule.effect as u8);
+            out.extend_from_slice(&rule.operations.to_be_bytes());
+            out.push(rule.data);
+            match rule.target {
+                Target::Any => out.push(0),
+                Target::SelfCell => out.push(1),
+                Target::Region { min, max } => {
+                    out.push(2);
+                    for v in [min.0, min.1, min.2, max.0, max.1, max.2] {
+                        out.extend_from_slice(&v.to_be_bytes());
+                    }
+                }
+            }
+        }
+
+        let mut constraints = self.constraints.clone();
+        constraints.sort();
+        constraints.dedup();
+        out.extend_from_slice(&count(constraints.len())?.to_be_bytes());
+        for c in constraints {
+            out.push(c.tag());
+            out.extend_from_slice(&c.value().to_be_bytes());
+        }
+        Ok(out)
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, PolicyError> {
+        let mut r = Reader { data, at: 0 };
+
+        let intent_len = r.u16()? as usize;
+        let intent = r.take(intent_len)?.to_vec();
+
+        let rule_count = r.u16()?;
+        let mut scope = Vec::with_capacity(rule_count as usize);
+        for _ in 0..rule_count {
+            let effect = match r.u8()? {
+                0 => Effect::Deny,
+                1 => Effect::Allow,
+                t => return Err(PolicyError::UnknownTag(t)),
+            };
+            let operations = r.u16()?;
+            let data = r.u8()?;
+            if operations & !Operation::ALL_BITS != 0 || data & !DataClass::ALL_BITS != 0 {
+                return Err(PolicyError::UnknownBits);
+            }
+            let target = match r.u8()? {
+                0 => Target::Any,
+                1 => Target::SelfCell,
+                2 => {
+                    let mut v = [0i16; 6];
+                    for slot in v.iter_mut() {
+                        *slot = r.u16()? as i16;
+                    }
+                    Target::Region {
+                        min: (v[0], v[1], v[2]),
+                        max: (v[3], v[4], v[5]),
+                    }
+                }
+                t => return Err(PolicyError::UnknownTag(t)),
+            };
+            scope.push(ScopeRule {
+                effect,
+                operations,
+                data,
+                target,
+            });
+        }
+
+        let constraint_count = r.u16()?;
+        let mut constraints = Vec::with_capacity(constraint_count as usize);
+        for _ in 0..constraint_count {
+            let tag = r.u8()?;
+            let value = r.u64()?;
+            constraints.push(match tag {
+                1 => HardConstraint::MaxReplications(value),
+                2 => HardConstraint::Deadline(value),
+                t => return Err(PolicyError::UnknownTag(t)),
+            });
+        }
+
+        if r.at != data.len() {
+            return Err(PolicyError::TrailingBytes);
+        }
+        Ok(Self {
+            intent,
+            scope,
+            constraints,
+        })
+    }
+
+    // Decodes and checks that the bytes are already canonical (Spec Section 1.4)
+    pub fn decode_canonical(data: &[u8]) -> Result<Self, PolicyError> {
+        let core = Self::decode(data)?;
+        if core.encode()? != data {
+            return Err(PolicyError::NonCanonical);
+        }
+        Ok(core)
+    }
+}
+
+// A length as its u16 count field
+fn count(len: usize) -> Result<u16, PolicyError> {
+    u16::try_from(len).map_err(|_| PolicyError::TooLong)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], PolicyError> {
+        let end = self.at.checked_add(n).ok_or(PolicyError::Truncated)?;
+        let slice = self.data.get(self.at..end).ok_or(PolicyError::Truncated)?;
+        self.at = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, PolicyError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, PolicyError> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, PolicyError> {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(u64::from_be_bytes(b))
+    }
+}
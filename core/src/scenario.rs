@@ -0,0 +1,217 @@
+use crate::asm::{self, AsmError};
+use crate::capsules::{Capsule, CapsuleHeader, CapsuleRole, ExecState, SquareSpace};
+use crate::codec::HEADER_LEN;
+use crate::config::VmConfig;
+use crate::error::VmError;
+use crate::lattice::Coord;
+use crate::policy::PolicyCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+// A declarative starting lattice. Scenario files are JSON:
+//
+// {
+//   "name": "relay",
+//   "config": { "write_policy": "Merge" },          (optional)
+//   "capsules": [
+//     { "id": 1, "at": [0, 0, 0], "role": "Kernel", "code": "INC LOG HALT" },
+//     { "id": 100, "at": [1, 0, 0], "role": "Structure", "size": 64 }
+//   ]
+// }
+//
+// Capsule fields other than id and at are optional: role (UserProgram),
+// code (BASM, empty), size (minimum payload length, zero padded), flags,
+// priority, policy (a PolicyCore). Each capsule is sealed into the smallest
+// SquareSpace that holds it.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub config: VmConfig,
+    pub capsules: Vec<ScenarioCapsule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioCapsule {
+    pub id: u32,
+    pub at: Coord,
+    #[serde(default)]
+    pub role: CapsuleRole,
+    #[serde(default)]
+    pub code: String,
+    #[serde(default)]
+    pub size: usize,
+    #[serde(default)]
+    pub flags: u16,
+    #[serde(default)]
+    pub priority: u8,
+    #[serde(default)]
+    pub policy: Option<PolicyCore>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScenarioError {
+    Parse(String),
+    UnknownScenario(String),
+    DuplicateId(u32),
+    Asm { capsule_id: u32, error: AsmError },
+    // The capsule does not fit in the largest SquareSpace
+    TooLarge(u32),
+    Rejected(VmError),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Parse(e) => write!(f, "invalid scenario: {}", e),
+            ScenarioError::UnknownScenario(n) => write!(f, "no built-in scenario '{}'", n),
+            ScenarioError::DuplicateId(id) => write!(f, "capsule id {} is used twice", id),
+            ScenarioError::Asm { capsule_id, error } => {
+                write!(f, "capsule {}: {}", capsule_id, error)
+            }
+            ScenarioError::TooLarge(id) => write!(f, "capsule {} does not fit in SS128", id),
+            ScenarioError::Rejected(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl Scenario {
+    pub const BUILTIN: [&'static str; 2] = ["star-fortress", "empty"];
+
+    pub fn from_json(text: &str) -> Result<Self, ScenarioError> {
+        serde_json::from_str(text).map_err(|e| ScenarioError::Parse(e.to_string()))
+    }
+
+    pub fn builtin(name: &str) -> Result<Self, ScenarioError> {
+        match name {
+            "star-fortress" => Ok(Self::star_fortress()),
+            "empty" => Ok(Self {
+                name: "empty".to_string(),
+                config: VmConfig::default(),
+                capsules: Vec::new(),
+            }),
+            _ => Err(ScenarioError::UnknownScenario(name.to_string())),
+        }
+    }
+
+    // The default world: six dormant structure arms of length 8 around an
+    // empty origin, the kernel at (10,0,0) and the oracle at (-10,0,0).
+    // The origin is left free for the programs the host injects.
+    pub fn star_fortress() -> Self {
+        let node = |id, at, role, flags| ScenarioCapsule {
+            id,
+            at,
+            role,
+            code: String::new(),
+            size: 64,
+            flags,
+            priority: 0,
+            policy: None,
+        };
+
+        let mut capsules = vec![
+            node(1, (10, 0, 0), CapsuleRole::Kernel, 1),
+            node(5, (-10, 0, 0), CapsuleRole::OracleOutput, 7),
+        ];
+        let mut struct_id = 100;
+        for i in 1..=8 {
+            for at in [
+                (i, 0, 0),
+                (-i, 0, 0),
+                (0, i, 0),
+                (0, -i, 0),
+                (0, 0, i),
+                (0, 0, -i),
+            ] {
+                capsules.push(node(struct_id, at, CapsuleRole::Structure, 2));
+                struct_id += 1;
+            }
+        }
+
+        Self {
+            name: "star-fortress".to_string(),
+            config: VmConfig::default(),
+            capsules,
+        }
+    }
+
+    // Assembles and seals every capsule, in file order.
+    pub fn build(&self) -> Result<Vec<Capsule>, ScenarioError> {
+        let mut seen = HashSet::new();
+        self.capsules
+            .iter()
+            .map(|spec| {
+                if !seen.insert(spec.id) {
+                    return Err(ScenarioError::DuplicateId(spec.id));
+                }
+                spec.build()
+            })
+            .collect()
+    }
+}
+
+impl ScenarioCapsule {
+    fn build(&self) -> Result<Capsule, ScenarioError> {
+        let mut payload = asm::assemble(&self.code).map_err(|error| ScenarioError::Asm {
+            capsule_id: self.id,
+            error,
+        })?;
+        if payload.len() < self.size {
+            payload.resize(self.size, 0);
+        }
+        // A core too long to encode would not fit a frame either
+        let policy_core = self
+            .policy
+            .as_ref()
+            .map(PolicyCore::encode)
+            .transpose()
+            .map_err(|_| ScenarioError::TooLarge(self.id))?
+            .unwrap_or_default();
+
+        let needed = HEADER_LEN + policy_core.len() + payload.len();
+        let ss_n = [
+            SquareSpace::SS8,
+            SquareSpace::SS16,
+            SquareSpace::SS32,
+            SquareSpace::SS64,
+            SquareSpace::SS128,
+        ]
+        .into_iter()
+        .find(|ss| (*ss as usize).pow(3) >= needed)
+        .ok_or(ScenarioError::TooLarge(self.id))?;
+
+        let mut capsule = Capsule {
+            header: CapsuleHeader {
+                magic: *b"BLE1",
+                version_major: 0,
+                version_minor: 1,
+                flags: self.flags,
+                capsule_id: self.id,
+                ss_n,
+                priority: self.priority,
+                coord_x: self.at.0,
+                coord_y: self.at.1,
+                coord_z: self.at.2,
+                header_len: 0,
+                policy_len: 0,
+                payload_len: 0,
+                pad_len: 0,
+                dict_hash: [0; 32],
+                policy_core_hash: [0; 32],
+                capsule_hash: [0; 32],
+            },
+            policy_core,
+            payload,
+            state: ExecState {
+                role: self.role,
+                ..ExecState::default()
+            },
+        };
+        crate::integrity::seal(&mut capsule).map_err(|_| ScenarioError::TooLarge(self.id))?;
+        Ok(capsule)
+    }
+}
@@ -0,0 +1,240 @@
+use crate::capsules::{Capsule, CapsuleRole};
+use crate::config::VmConfig;
+use crate::trap::Trap;
+use crate::vm::LatticeVM;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+// Determinism tooling: a canonical digest of the VM state, and a recorder
+// that captures everything a run depends on (starting state, injections,
+// per-cycle digests) so the run can be replayed and checked.
+
+impl LatticeVM {
+    // SHA-256 over cycle_count, next_id, the config, both queues (every
+    // header field, Policy Core, payload and ExecState), the output buffer
+    // and the halt reason. Pending events and the trace itself are not part
+    // of the state.
+    pub fn state_digest(&self) -> [u8; 32] {
+        let mut h = Sha256::new();
+        h.update(self.cycle_count.to_be_bytes());
+        h.update(self.next_id.to_be_bytes());
+        digest_config(&mut h, &self.config);
+        for queue in [&self.active_queue, &self.next_queue] {
+            h.update((queue.len() as u64).to_be_bytes());
+            for capsule in queue {
+                digest_capsule(&mut h, capsule);
+            }
+        }
+        h.update((self.output_buffer.len() as u64).to_be_bytes());
+        for line in &self.output_buffer {
+            h.update((line.len() as u64).to_be_bytes());
+            h.update(line.as_bytes());
+        }
+        match &self.halted {
+            None => h.update([0]),
+            Some(err) => {
+                // Nested and rare, so it goes in as its JSON form
+                h.update([1]);
+                h.update(serde_json::to_vec(err).expect("VmError serializes"));
+            }
+        }
+        h.finalize().into()
+    }
+}
+
+fn digest_config(h: &mut Sha256, config: &VmConfig) {
+    h.update([
+        config.scheduling as u8,
+        config.write_policy as u8,
+        config.require_policy_core as u8,
+        config.move_policy as u8,
+        config.occupancy as u8,
+        config.repl_policy as u8,
+    ]);
+    h.update(config.instruction_budget.to_be_bytes());
+    match config.cycle_gas {
+        None => h.update([0]),
+        Some(gas) => {
+            h.update([1]);
+            h.update(gas.to_be_bytes());
+        }
+    }
+}
+
+fn digest_capsule(h: &mut Sha256, c: &Capsule) {
+    let hd = &c.header;
+    h.update(hd.magic);
+    h.update([
+        hd.version_major,
+        hd.version_minor,
+        hd.ss_n as u8,
+        hd.priority,
+    ]);
+    h.update(hd.flags.to_be_bytes());
+    h.update(hd.header_len.to_be_bytes());
+    h.update(hd.policy_len.to_be_bytes());
+    h.update(hd.payload_len.to_be_bytes());
+    h.update(hd.pad_len.to_be_bytes());
+    for v in [hd.coord_x, hd.coord_y, hd.coord_z] {
+        h.update(v.to_be_bytes());
+    }
+    h.update(hd.capsule_id.to_be_bytes());
+    h.update(hd.dict_hash);
+    h.update(hd.policy_core_hash);
+    h.update(hd.capsule_hash);
+
+    h.update((c.policy_core.len() as u64).to_be_bytes());
+    h.update(&c.policy_core);
+    h.update((c.payload.len() as u64).to_be_bytes());
+    h.update(&c.payload);
+
+    let s = &c.state;
+    h.update(s.ip.to_be_bytes());
+    for r in s.registers {
+        h.update(r.to_be_bytes());
+    }
+    h.update([s.status as u8, s.role as u8]);
+    h.update(s.wait.to_be_bytes());
+    h.update(s.replications.to_be_bytes());
+    match s.fault {
+        None => h.update([0]),
+        Some(trap) => digest_trap(h, trap),
+    }
+    match s.handler {
+        None => h.update([0]),
+        Some(at) => {
+            h.update([1]);
+            h.update(at.to_be_bytes());
+        }
+    }
+    h.update((s.mailbox.len() as u64).to_be_bytes());
+    for m in &s.mailbox {
+        h.update(m.from.to_be_bytes());
+        h.update(m.value.to_be_bytes());
+    }
+    h.update((s.call_stack.len() as u64).to_be_bytes());
+    for at in &s.call_stack {
+        h.update(at.to_be_bytes());
+    }
+    h.update([s.flags]);
+}
+
+fn digest_trap(h: &mut Sha256, trap: Trap) {
+    h.update([trap.code()]);
+    match trap {
+        Trap::IllegalOpcode { byte } => h.update([byte]),
+        Trap::OperandUnderflow { opcode } => h.update([opcode as u8]),
+        Trap::JumpOutOfRange { target } => h.update(target.to_be_bytes()),
+        Trap::SliceOutOfRange { start, len } => {
+            h.update(start.to_be_bytes());
+            h.update(len.to_be_bytes());
+        }
+        Trap::CapacityExceeded
+        | Trap::StackOverflow
+        | Trap::StackUnderflow
+        | Trap::DivideByZero
+        | Trap::IdsExhausted => {}
+        Trap::MoveBlocked { target } | Trap::CellOccupied { target } => {
+            for v in [target.0, target.1, target.2] {
+                h.update(v.to_be_bytes());
+            }
+        }
+        Trap::PolicyViolation { operation, target } => {
+            h.update([operation as u8]);
+            for v in [target.0, target.1, target.2] {
+                h.update(v.to_be_bytes());
+            }
+        }
+    }
+}
+
+// A capsule admitted while cycle_count was `cycle`, i.e. before cycle + 1 ran
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Injection {
+    pub cycle: u64,
+    pub role: CapsuleRole,
+    pub capsule: Capsule,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Trace {
+    // The VM as it was when recording started
+    pub base: Box<LatticeVM>,
+    pub injections: Vec<Injection>,
+    // (cycle_count, state_digest) after every cycle that ran
+    pub digests: Vec<(u64, [u8; 32])>,
+}
+
+// First point where a replay differs from the recording
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub cycle: u64,
+    pub expected: [u8; 32],
+    pub actual: [u8; 32],
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "state diverged at cycle {}: expected {}, got {}",
+            self.cycle,
+            hex(&self.expected),
+            hex(&self.actual)
+        )
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl Trace {
+    // Re-runs the recording from `base`, feeding each injection in at its
+    // cycle, and checks every digest. Returns the replayed VM.
+    pub fn replay(&self) -> Result<LatticeVM, Divergence> {
+        let mut vm = (*self.base).clone();
+        vm.rebuild_index();
+        let mut injections = self.injections.iter().peekable();
+
+        for &(cycle, expected) in &self.digests {
+            while vm.cycle_count < cycle {
+                while let Some(inj) = injections.next_if(|i| i.cycle == vm.cycle_count) {
+                    // It was admitted when recorded, so it is admitted again
+                    let _ = vm.activate_with_role(inj.capsule.clone(), inj.role);
+                }
+                let _ = vm.next_cycle();
+            }
+            let actual = vm.state_digest();
+            if actual != expected {
+                return Err(Divergence {
+                    cycle,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        for inj in injections {
+            let _ = vm.activate_with_role(inj.capsule.clone(), inj.role);
+        }
+        Ok(vm)
+    }
+
+    #[cfg(feature = "cli-mode")]
+    pub fn save(&self, filename: &str) -> std::io::Result<()> {
+        let encoded = bincode::serialize(self).map_err(std::io::Error::other)?;
+        std::fs::write(filename, encoded)
+    }
+
+    #[cfg(feature = "cli-mode")]
+    pub fn load(filename: &str) -> std::io::Result<Self> {
+        let data = std::fs::read(filename)?;
+        let mut trace: Self = bincode::deserialize(&data).map_err(std::io::Error::other)?;
+        // The index is not saved with the base VM
+        trace.base.rebuild_index();
+        Ok(trace)
+    }
+}
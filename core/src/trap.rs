@@ -0,0 +1,86 @@
+use crate::instructions::OpCode;
+use crate::lattice::Coord;
+use crate::policy::Operation;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Why an instruction could not execute. A trapping instruction has no
+// effect: the capsule jumps to its fault handler (see ONFAULT) or, without
+// one, is left Faulted at the offending instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Trap {
+    // A byte that is not in the opcode table
+    IllegalOpcode { byte: u8 },
+    // The operands run past the end of the payload
+    OperandUnderflow { opcode: OpCode },
+    // JMP, a taken branch or ONFAULT aimed outside the payload
+    JumpOutOfRange { target: u32 },
+    // A child or a write to the capsule itself that does not fit its frame
+    CapacityExceeded,
+    // Denied by SCOPE_FILTER / HARD_CONSTRAINTS, or an undecodable Policy Core
+    PolicyViolation { operation: Operation, target: Coord },
+    // CALL with the call stack already full
+    StackOverflow,
+    // RET with nothing to return to
+    StackUnderflow,
+    // DIV or MOD by a zero register
+    DivideByZero,
+    // MOVE into an occupied cell, under MovePolicy::Trap
+    MoveBlocked { target: Coord },
+    // REPL or SPAWN into a taken cell, under ReplPolicy::Fail
+    CellOccupied { target: Coord },
+    // SPAWN of bytes past the end of the payload
+    SliceOutOfRange { start: u32, len: u32 },
+    // REPL or SPAWN after every capsule id has been handed out
+    IdsExhausted,
+}
+
+impl Trap {
+    // Number handed to a fault handler in R0
+    pub fn code(&self) -> u8 {
+        match self {
+            Trap::IllegalOpcode { .. } => 1,
+            Trap::OperandUnderflow { .. } => 2,
+            Trap::JumpOutOfRange { .. } => 3,
+            Trap::CapacityExceeded => 4,
+            Trap::PolicyViolation { .. } => 5,
+            Trap::StackOverflow => 6,
+            Trap::StackUnderflow => 7,
+            Trap::DivideByZero => 8,
+            Trap::MoveBlocked { .. } => 9,
+            Trap::CellOccupied { .. } => 10,
+            Trap::SliceOutOfRange { .. } => 11,
+            Trap::IdsExhausted => 12,
+        }
+    }
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trap::IllegalOpcode { byte } => write!(f, "illegal opcode {}", byte),
+            Trap::OperandUnderflow { opcode } => {
+                write!(f, "{} is missing operands", opcode.mnemonic())
+            }
+            Trap::JumpOutOfRange { target } => write!(f, "jump to {} is out of range", target),
+            Trap::CapacityExceeded => write!(f, "capacity exceeded"),
+            Trap::PolicyViolation {
+                operation,
+                target: (x, y, z),
+            } => write!(f, "policy denies {:?} on ({},{},{})", operation, x, y, z),
+            Trap::StackOverflow => write!(f, "call stack overflow"),
+            Trap::StackUnderflow => write!(f, "RET with an empty call stack"),
+            Trap::DivideByZero => write!(f, "division by zero"),
+            Trap::MoveBlocked { target: (x, y, z) } => {
+                write!(f, "move to ({},{},{}) is blocked", x, y, z)
+            }
+            Trap::CellOccupied { target: (x, y, z) } => {
+                write!(f, "cell ({},{},{}) is occupied", x, y, z)
+            }
+            Trap::SliceOutOfRange { start, len } => {
+                write!(f, "{} bytes from {} run past the payload", len, start)
+            }
+            Trap::IdsExhausted => write!(f, "no capsule ids are left"),
+        }
+    }
+}
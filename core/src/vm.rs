@@ -1,77 +1,225 @@
-use crate::capsules::{Capsule, CapsuleHeader, SquareSpace};
+use crate::capsules::{
+    Capsule, CapsuleHeader, CapsuleRole, CapsuleStatus, ExecState, Message, SquareSpace,
+    FLAG_BLOCKED, FLAG_EQUAL, FLAG_LESS,
+};
+use crate::codec::{LatticeCodec, HEADER_LEN};
+use crate::config::{MovePolicy, Occupancy, ReplPolicy, SchedulingMode, VmConfig, WritePolicy};
+use crate::error::VmError;
+use crate::events::VmEvent;
+use crate::history::History;
 use crate::instructions::OpCode;
+use crate::integrity;
+use crate::lattice::{Coord, SpatialIndex};
+use crate::policy::{DataClass, Operation, PolicyCore};
+use crate::scenario::{Scenario, ScenarioError};
+use crate::trace::{Injection, Trace};
+use crate::trap::Trap;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
-#[derive(Serialize, Deserialize)]
+// Ids below this are reserved for capsules the host installs (genesis).
+pub const FIRST_USER_ID: u32 = 1000;
+
+// Messages a mailbox holds; SENDs to a full mailbox are dropped.
+pub const MAILBOX_CAPACITY: usize = 16;
+
+// Return addresses a capsule may have outstanding; one more CALL traps.
+pub const CALL_STACK_DEPTH: usize = 16;
+
+// A STORE, resolved at the cycle boundary. `source` is the writer's place
+// in active_queue and `slot` its place in next_queue (None if it left the
+// lattice this cycle); `at` is the offset of the STORE instruction. A
+// `local` write went to the writer's own cell and is already in its payload;
+// it is recorded only so remote writes to the same byte conflict with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingWrite {
+    pub writer: u32,
+    pub source: usize,
+    pub slot: Option<usize>,
+    pub target: Coord,
+    pub idx: usize,
+    pub value: u8,
+    pub at: u32,
+    pub local: bool,
+}
+
+// A SEND, delivered to the mailbox at `target` at the cycle boundary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingMessage {
+    pub from: u32,
+    pub target: Coord,
+    pub value: i32,
+}
+
+// A MOVE, carried out at the cycle boundary. `slot` is the mover's place in
+// next_queue, `at` the offset of the MOVE instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingMove {
+    pub slot: usize,
+    pub target: Coord,
+    pub at: u32,
+}
+
+// What stepping one capsule does outside that capsule, in execution order.
+// Steps only read the lattice; run_cycle applies these afterwards, which is
+// what lets the parallel executor step capsules concurrently.
+enum Effect {
+    Event(VmEvent),
+    Output(String),
+    Write(PendingWrite),
+    Send(PendingMessage),
+    Activate(Coord),
+    Move {
+        target: Coord,
+        at: u32,
+    },
+    // A child still without an id; ids are handed out when it is applied.
+    Birth {
+        parent: u32,
+        child: Box<Capsule>,
+        spawned: bool, // SPAWN child rather than REPL clone
+        at: u32,       // Offset of the REPL or SPAWN
+    },
+}
+
+// A child waiting to be placed at the cycle boundary. `parent` is the
+// parent's slot in next_queue, None if it left the lattice this cycle.
+struct Birth {
+    child: Capsule,
+    parent_id: u32,
+    parent: Option<usize>,
+    spawned: bool,
+    at: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct LatticeVM {
     pub active_queue: Vec<Capsule>,
     pub next_queue: Vec<Capsule>,
     pub cycle_count: u64,
-    pub registers: [i32; 4],
     pub next_id: u32,
     pub universe_id: String,
     pub output_buffer: Vec<String>,
+    // Not saved: a loaded world starts with an empty event queue.
+    #[serde(skip)]
+    pub events: Vec<VmEvent>,
+    pub config: VmConfig,
+    // Set when a fail-closed check trips; the VM refuses to run afterwards.
+    pub halted: Option<VmError>,
+    #[serde(skip)]
+    pub pending_writes: Vec<PendingWrite>,
+    #[serde(skip)]
+    pub pending_messages: Vec<PendingMessage>,
+    #[serde(skip)]
+    pub pending_moves: Vec<PendingMove>,
+    // Cells activated this cycle, woken at the cycle boundary. A set, so many
+    // activators of one cell wake it once, in a deterministic order.
+    #[serde(skip)]
+    pub pending_activations: BTreeSet<Coord>,
+    // Coordinate index over next_queue. Not saved; rebuilt on load.
+    #[serde(skip)]
+    index: SpatialIndex,
+    // Recording in progress, if any (see start_trace)
+    #[serde(skip)]
+    trace: Option<Trace>,
+    // Snapshot ring for rewinding, if enabled (see history.rs)
+    #[serde(skip)]
+    pub(crate) history: Option<History>,
+    // Step capsules on the rayon pool (see set_parallel)
+    #[cfg(feature = "parallel")]
     #[serde(skip)]
-    pub pending_writes: Vec<(i16, i16, i16, usize, u8)>,
+    parallel: bool,
 }
 
 impl LatticeVM {
+    // A VM built from the default Star Fortress scenario
     pub fn new(id: String) -> Self {
-        let mut vm = Self {
+        Self::with_scenario(id, &Scenario::star_fortress()).expect("built-in scenario is valid")
+    }
+
+    // A VM with no capsules and default settings
+    pub fn empty(id: String) -> Self {
+        Self {
             active_queue: Vec::new(),
             next_queue: Vec::new(),
             cycle_count: 0,
-            registers: [0; 4],
-            next_id: 1000,
+            next_id: FIRST_USER_ID,
             universe_id: id,
             output_buffer: Vec::new(),
+            events: Vec::new(),
+            config: VmConfig::default(),
+            halted: None,
             pending_writes: Vec::new(),
-        };
-        vm.genesis();
-        vm
-    }
-
-    pub fn genesis(&mut self) {
-        println!("> [INIT] Constructing Star Fortress Architecture...");
-        self.spawn_node(1, 0, 0, 0, 1);
-        self.spawn_node(5, -10, 0, 0, 7);
-
-        let mut struct_id = 100;
-        let arm_length = 8;
-        for i in 1..=arm_length {
-            self.spawn_node(struct_id, i, 0, 0, 2);
-            struct_id += 1;
-            self.spawn_node(struct_id, -i, 0, 0, 2);
-            struct_id += 1;
-            self.spawn_node(struct_id, 0, i, 0, 2);
-            struct_id += 1;
-            self.spawn_node(struct_id, 0, -i, 0, 2);
-            struct_id += 1;
-            self.spawn_node(struct_id, 0, 0, i, 2);
-            struct_id += 1;
-            self.spawn_node(struct_id, 0, 0, -i, 2);
-            struct_id += 1;
-        }
-        println!(
-            "> [SYSTEM] Star Fortress Online. Nodes: {}",
-            self.next_queue.len()
-        );
+            pending_messages: Vec::new(),
+            pending_moves: Vec::new(),
+            pending_activations: BTreeSet::new(),
+            index: SpatialIndex::default(),
+            trace: None,
+            history: None,
+            #[cfg(feature = "parallel")]
+            parallel: false,
+        }
     }
 
-    fn spawn_node(&mut self, id: u32, x: i16, y: i16, z: i16, flag: u16) {
+    // A VM whose capsules and settings come from `scenario`
+    pub fn with_scenario(id: String, scenario: &Scenario) -> Result<Self, ScenarioError> {
+        let mut vm = Self::empty(id);
+        vm.config = scenario.config.clone();
+        vm.load_scenario(scenario)?;
+        Ok(vm)
+    }
+
+    // Installs the Star Fortress into this VM. Fails if one of its capsules
+    // is refused, e.g. because this VM already holds that cell or id.
+    pub fn genesis(&mut self) -> Result<(), ScenarioError> {
+        self.load_scenario(&Scenario::star_fortress())
+    }
+
+    // Adds a scenario's capsules in their declared roles. Nothing is placed
+    // unless every capsule builds and would be admitted, alongside the
+    // others as well as into this VM.
+    pub fn load_scenario(&mut self, scenario: &Scenario) -> Result<(), ScenarioError> {
+        let capsules = scenario.build()?;
+        // build() has already refused ids used twice
+        let mut cells = HashSet::new();
+        for capsule in &capsules {
+            self.check_admission(capsule)
+                .map_err(ScenarioError::Rejected)?;
+            let at = capsule.coord();
+            if self.config.occupancy == Occupancy::Exclusive && !cells.insert(at) {
+                return Err(ScenarioError::Rejected(VmError::Occupied {
+                    capsule_id: capsule.header.capsule_id,
+                    at,
+                }));
+            }
+        }
+        for capsule in capsules {
+            let role = capsule.state.role;
+            self.place_admitted(capsule, role);
+        }
+        Ok(())
+    }
+
+    // A dormant structure node with 64 zero bytes of payload under the
+    // parent's Policy Core, if it fits. Code stored into it later stays
+    // governed. Sealed once it has an id.
+    fn brick(parent: &Capsule, at: Coord) -> Option<Capsule> {
         let payload = vec![0u8; 64];
-        let cap = Capsule {
+        let mut cap = Capsule {
             header: CapsuleHeader {
                 magic: *b"BLE1",
                 version_major: 0,
                 version_minor: 1,
-                flags: flag,
-                capsule_id: id,
-                ss_n: SquareSpace::SS64,
+                flags: 2,
+                capsule_id: 0,
+                // 64 bytes of payload fit in SS8; a smaller frame keeps sealing cheap
+                ss_n: SquareSpace::SS8,
                 priority: 0,
-                coord_x: x,
-                coord_y: y,
-                coord_z: z,
+                coord_x: at.0,
+                coord_y: at.1,
+                coord_z: at.2,
                 header_len: 0,
                 policy_len: 0,
                 payload_len: payload.len() as u32,
@@ -80,23 +228,99 @@ impl LatticeVM {
                 policy_core_hash: [0; 32],
                 capsule_hash: [0; 32],
             },
-            policy_core: vec![],
+            policy_core: parent.policy_core.clone(),
+            payload,
+            state: ExecState {
+                status: CapsuleStatus::Dormant,
+                role: CapsuleRole::Structure,
+                ..ExecState::default()
+            },
+        };
+        if LatticeCodec::fill_lengths(&mut cap).is_err() {
+            // A Policy Core too long for SS8 still fits the parent's frame
+            cap.header.ss_n = parent.header.ss_n;
+            LatticeCodec::fill_lengths(&mut cap).ok()?;
+        }
+        Some(cap)
+    }
+
+    // A child of `parent` running `payload`, if it fits the parent's frame.
+    // It keeps the frame size, priority, flags and Policy Core, but none of
+    // the parent's state. Sealed once it has an id.
+    fn offspring(parent: &Capsule, at: Coord, payload: Vec<u8>) -> Option<Capsule> {
+        let mut child = Capsule {
+            header: parent.header.clone(),
+            policy_core: parent.policy_core.clone(),
             payload,
+            state: ExecState::default(),
         };
-        self.next_queue.push(cap);
+        child.header.coord_x = at.0;
+        child.header.coord_y = at.1;
+        child.header.coord_z = at.2;
+        LatticeCodec::fill_lengths(&mut child).ok()?;
+        Some(child)
+    }
+
+    // Appends a capsule to next_queue and records its cell in the index.
+    fn place(&mut self, capsule: Capsule) {
+        self.index.insert(capsule.coord(), self.next_queue.len());
+        self.next_queue.push(capsule);
+    }
+
+    // The capsule currently occupying (x, y, z), if any. next_queue is
+    // public, so the index may name a slot it no longer has; call
+    // rebuild_index() after changing the queue directly.
+    pub fn capsule_at(&self, x: i16, y: i16, z: i16) -> Option<&Capsule> {
+        self.index
+            .get((x, y, z))
+            .and_then(|slot| self.next_queue.get(slot))
+    }
+
+    // Where to put a capsule the host wants at `at`: `at` itself when it is
+    // free or cells stack, else the nearest free cell, searching shells of
+    // growing radius around it in x, y, z order.
+    pub fn free_cell_near(&self, at: Coord) -> Coord {
+        if self.config.occupancy == Occupancy::Stacked {
+            return at;
+        }
+        for r in 0..=i16::MAX {
+            for dx in -r..=r {
+                for dy in -r..=r {
+                    for dz in -r..=r {
+                        if dx.abs().max(dy.abs()).max(dz.abs()) != r {
+                            continue;
+                        }
+                        let cell = (
+                            at.0.wrapping_add(dx),
+                            at.1.wrapping_add(dy),
+                            at.2.wrapping_add(dz),
+                        );
+                        if self.index.get(cell).is_none() {
+                            return cell;
+                        }
+                    }
+                }
+            }
+        }
+        at
+    }
+
+    // Re-syncs the index after next_queue was modified directly.
+    pub fn rebuild_index(&mut self) {
+        self.index.rebuild(&self.next_queue);
     }
 
     #[cfg(feature = "cli-mode")]
     pub fn save_world(&self, filename: &str) -> std::io::Result<()> {
         use std::fs::File;
         use std::io::Write;
-        let encoded: Vec<u8> = bincode::serialize(&self)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        let encoded = crate::world::encode(self)?;
         let mut file = File::create(filename)?;
         file.write_all(&encoded)?;
         Ok(())
     }
 
+    // Also accepts pre-versioning universe files and migrates them.
     #[cfg(feature = "cli-mode")]
     pub fn load_world(filename: &str) -> std::io::Result<Self> {
         use std::fs::File;
@@ -104,221 +328,1484 @@ impl LatticeVM {
         let mut file = File::open(filename)?;
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)?;
-        let decoded: Self = bincode::deserialize(&buffer)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-        Ok(decoded)
+        crate::world::decode(&buffer)
     }
 
-    pub fn activate(&mut self, capsule: Capsule) {
-        self.next_queue.push(capsule);
+    // Admits a capsule into the next cycle after the DECODE -> VERIFY
+    // ceremony. Capsules that fail verification are refused (fail-closed).
+    // They run as user programs and may not use a reserved id, so no
+    // injected capsule can stand in for a genesis node.
+    pub fn activate(&mut self, capsule: Capsule) -> Result<(), VmError> {
+        self.activate_with_role(capsule, CapsuleRole::UserProgram)
+    }
+
+    // activate() for capsules the host installs in a specific role.
+    pub fn activate_with_role(
+        &mut self,
+        capsule: Capsule,
+        role: CapsuleRole,
+    ) -> Result<(), VmError> {
+        if role == CapsuleRole::UserProgram && capsule.header.capsule_id < FIRST_USER_ID {
+            return Err(VmError::ReservedId {
+                capsule_id: capsule.header.capsule_id,
+            });
+        }
+        self.admit(capsule, role)
+    }
+
+    // VERIFY, then place in `role`. The host chooses ids; one already on
+    // the lattice, or u32::MAX, is refused. Runtime ids are allocated above
+    // every admitted id, so a child never shares one with a capsule the host
+    // placed.
+    fn admit(&mut self, capsule: Capsule, role: CapsuleRole) -> Result<(), VmError> {
+        self.check_admission(&capsule)?;
+        self.place_admitted(capsule, role);
+        Ok(())
+    }
+
+    // Everything admit() checks before it places a capsule
+    fn check_admission(&self, capsule: &Capsule) -> Result<(), VmError> {
+        integrity::verify(capsule).map_err(|reason| VmError::Rejected {
+            capsule_id: capsule.header.capsule_id,
+            reason,
+        })?;
+        if capsule.header.capsule_id == u32::MAX {
+            return Err(VmError::IdOutOfRange {
+                capsule_id: capsule.header.capsule_id,
+            });
+        }
+        if self.config.occupancy == Occupancy::Exclusive
+            && self.index.get(capsule.coord()).is_some()
+        {
+            return Err(VmError::Occupied {
+                capsule_id: capsule.header.capsule_id,
+                at: capsule.coord(),
+            });
+        }
+        let id = capsule.header.capsule_id;
+        if self.next_queue.iter().any(|c| c.header.capsule_id == id) {
+            return Err(VmError::DuplicateId { capsule_id: id });
+        }
+        Ok(())
     }
 
-    pub fn next_cycle(&mut self) {
+    // Places a capsule that passed check_admission()
+    fn place_admitted(&mut self, mut capsule: Capsule, role: CapsuleRole) {
+        if self.trace.is_some() || self.history.is_some() {
+            let injection = Injection {
+                cycle: self.cycle_count,
+                role,
+                capsule: capsule.clone(),
+            };
+            self.journal_injection(&injection);
+            if let Some(trace) = &mut self.trace {
+                trace.injections.push(injection);
+            }
+        }
+        capsule.state.role = role;
+        capsule.state.status = role.initial_status();
+        // Never u32::MAX, so there is room above it
+        self.next_id = self.next_id.max(capsule.header.capsule_id + 1);
+        self.place(capsule);
+    }
+
+    // Same as activate(), starting from a BLE frame.
+    pub fn activate_encoded(&mut self, data: &[u8]) -> Result<(), VmError> {
+        let capsule = LatticeCodec::decode(data).map_err(|e| VmError::Rejected {
+            capsule_id: 0,
+            reason: e.into(),
+        })?;
+        self.activate(capsule)
+    }
+
+    // Hands out a fresh CAPSULE_ID for capsules created at runtime, never
+    // one from the reserved range. None once every id below u32::MAX is
+    // taken.
+    pub fn allocate_id(&mut self) -> Option<u32> {
+        let id = self.next_id.max(FIRST_USER_ID);
+        self.next_id = id.checked_add(1)?;
+        Some(id)
+    }
+
+    // Orders the Active Queue for this cycle according to the scheduling mode.
+    fn schedule(&mut self) {
+        match self.config.scheduling {
+            SchedulingMode::Levin => {
+                // Stable sort: exact ties keep their queue order, so the
+                // earliest activation of a duplicated id is the one that fires.
+                // The other copies leave the lattice, each with an event.
+                self.active_queue.sort_by(|a, b| {
+                    a.header
+                        .priority
+                        .cmp(&b.header.priority)
+                        .then(a.header.coord_x.cmp(&b.header.coord_x))
+                        .then(a.header.coord_y.cmp(&b.header.coord_y))
+                        .then(a.header.coord_z.cmp(&b.header.coord_z))
+                });
+                let mut seen = HashSet::new();
+                let events = &mut self.events;
+                self.active_queue.retain(|c| {
+                    let first = seen.insert(c.header.capsule_id);
+                    if !first {
+                        events.push(VmEvent::Duplicate {
+                            capsule_id: c.header.capsule_id,
+                            at: c.coord(),
+                        });
+                    }
+                    first
+                });
+            }
+            SchedulingMode::Legacy => {
+                self.active_queue.sort_by(|a, b| {
+                    a.header
+                        .coord_z
+                        .cmp(&b.header.coord_z)
+                        .then(a.header.coord_y.cmp(&b.header.coord_y))
+                        .then(a.header.coord_x.cmp(&b.header.coord_x))
+                });
+            }
+        }
+    }
+
+    // Records from now on: the current state, every admitted capsule and the
+    // state digest after each cycle. Replaces any recording in progress.
+    pub fn start_trace(&mut self) {
+        self.trace = Some(Trace {
+            base: Box::new(self.detached_clone()),
+            injections: Vec::new(),
+            digests: Vec::new(),
+        });
+    }
+
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    pub fn stop_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    // A copy of the lattice state without the trace, history or pending
+    // events, for snapshots.
+    pub(crate) fn detached_clone(&mut self) -> LatticeVM {
+        let trace = self.trace.take();
+        let history = self.history.take();
+        let events = std::mem::take(&mut self.events);
+        let copy = self.clone();
+        self.trace = trace;
+        self.history = history;
+        self.events = events;
+        copy
+    }
+
+    pub fn next_cycle(&mut self) -> Result<(), VmError> {
+        let before = self.cycle_count;
+        let result = self.run_cycle();
+        if self.cycle_count != before {
+            if self.trace.is_some() {
+                let digest = self.state_digest();
+                if let Some(trace) = &mut self.trace {
+                    trace.digests.push((self.cycle_count, digest));
+                }
+            }
+            self.record_history();
+        }
+        result
+    }
+
+    fn run_cycle(&mut self) -> Result<(), VmError> {
+        if let Some(err) = &self.halted {
+            return Err(err.clone());
+        }
+
         self.active_queue = std::mem::take(&mut self.next_queue);
         self.cycle_count += 1;
 
-        self.active_queue.sort_by(|a, b| {
-            a.header
-                .coord_z
-                .cmp(&b.header.coord_z)
-                .then(a.header.coord_y.cmp(&b.header.coord_y))
-                .then(a.header.coord_x.cmp(&b.header.coord_x))
+        self.schedule();
+
+        // The active queue is this cycle's snapshot: steps read it but never
+        // change it.
+        let snapshot_index = SpatialIndex::build(&self.active_queue);
+        let stepped = self.step_all(&snapshot_index);
+
+        let mut birth_queue: Vec<Birth> = Vec::new();
+        self.index.clear();
+
+        for (source, (capsule, effects)) in stepped.into_iter().enumerate() {
+            let first_birth = birth_queue.len();
+            let first_write = self.pending_writes.len();
+            self.apply(effects, &mut birth_queue);
+            let parent = if capsule.state.status != CapsuleStatus::Voided {
+                self.place(capsule);
+                Some(self.next_queue.len() - 1)
+            } else {
+                self.events.push(VmEvent::Voided {
+                    capsule_id: capsule.header.capsule_id,
+                    at: capsule.coord(),
+                });
+                None
+            };
+            for birth in &mut birth_queue[first_birth..] {
+                birth.parent = parent;
+            }
+            for write in &mut self.pending_writes[first_write..] {
+                write.source = source;
+                write.slot = parent;
+            }
+        }
+
+        // Children are placed first so writes aimed at their cell land on them.
+        self.place_births(birth_queue);
+
+        // Moves before the other deferred effects, so those land on whoever
+        // holds the cell once the cycle's layout is final.
+        self.flush_moves();
+
+        if let Err(err) = self.flush_writes() {
+            self.halted = Some(err.clone());
+            return Err(err);
+        }
+        self.flush_messages();
+        self.flush_activations();
+        self.events.push(VmEvent::CycleCompleted {
+            cycle: self.cycle_count,
+            population: self.next_queue.len(),
         });
+        Ok(())
+    }
+
+    // Steps every capsule in the active queue against the snapshot, in queue
+    // order. Each gets the instruction budget, cut short once the cycle's gas
+    // runs out; capsules after that point get none.
+    fn step_all(&self, snapshot_index: &SpatialIndex) -> Vec<(Capsule, Vec<Effect>)> {
+        let budget = self.config.instruction_budget;
+        let step = |capsule: &Capsule, budget: u32| {
+            let mut capsule = capsule.clone();
+            let mut effects = Vec::new();
+            let used = self.step_capsule(&mut capsule, budget, snapshot_index, &mut effects, None);
+            (capsule, effects, used)
+        };
+        let mut gas = self.config.cycle_gas.unwrap_or(u64::MAX);
+
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            use rayon::prelude::*;
+            // Everything runs on the full budget first; the few capsules the
+            // gas limit cuts short are then re-run in order. collect() keeps
+            // queue order, so the result matches the sequential executor.
+            let mut stepped: Vec<_> = self
+                .active_queue
+                .par_iter()
+                .map(|c| step(c, budget))
+                .collect();
+            for (original, slot) in self.active_queue.iter().zip(stepped.iter_mut()) {
+                let allowed = Self::allowed(budget, gas);
+                // With no gas left even a capsule that ran nothing (say, one
+                // counting down a WAIT) must be left as it was
+                if slot.2 > allowed || allowed == 0 {
+                    *slot = step(original, allowed);
+                }
+                gas -= slot.2 as u64;
+            }
+            return stepped.into_iter().map(|(c, e, _)| (c, e)).collect();
+        }
+        self.active_queue
+            .iter()
+            .map(|c| {
+                let (capsule, effects, used) = step(c, Self::allowed(budget, gas));
+                gas -= used as u64;
+                (capsule, effects)
+            })
+            .collect()
+    }
+
+    // Instructions a capsule may run with `gas` left in the cycle
+    fn allowed(budget: u32, gas: u64) -> u32 {
+        gas.min(budget as u64) as u32
+    }
+
+    // Applies one capsule's effects. Runs sequentially in queue order, so ids
+    // and events come out the same whichever executor stepped the capsules.
+    fn apply(&mut self, effects: Vec<Effect>, birth_queue: &mut Vec<Birth>) {
+        for effect in effects {
+            match effect {
+                Effect::Event(event) => self.events.push(event),
+                Effect::Output(text) => self.output_buffer.push(text),
+                Effect::Write(write) => self.pending_writes.push(write),
+                Effect::Send(message) => self.pending_messages.push(message),
+                Effect::Activate(target) => {
+                    self.pending_activations.insert(target);
+                }
+                // MOVE ends the activation, so the mover is the next capsule
+                // placed
+                Effect::Move { target, at } => self.pending_moves.push(PendingMove {
+                    slot: self.next_queue.len(),
+                    target,
+                    at,
+                }),
+                Effect::Birth {
+                    parent,
+                    child,
+                    spawned,
+                    at,
+                } => birth_queue.push(Birth {
+                    child: *child,
+                    parent_id: parent,
+                    parent: None,
+                    spawned,
+                    at,
+                }),
+            }
+        }
+    }
+
+    // Steps capsules on the rayon pool. Results are bit-identical to the
+    // sequential executor; only the step phase is spread across threads.
+    #[cfg(feature = "parallel")]
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    // Names and places this cycle's children in execution order. Under
+    // exclusive occupancy a child aimed at a taken cell, by a survivor or an
+    // earlier child, follows the REPL policy.
+    fn place_births(&mut self, births: Vec<Birth>) {
+        for mut birth in births {
+            let cell = birth.child.coord();
+            let Some(id) = self.allocate_id() else {
+                // Unless an earlier child overwrote the parent
+                let parent = birth
+                    .parent
+                    .filter(|&slot| self.next_queue[slot].header.capsule_id == birth.parent_id);
+                if let Some(parent) = parent {
+                    self.set_blocked(parent, true);
+                    self.trap_at(parent, birth.at, Trap::IdsExhausted);
+                }
+                continue;
+            };
+            birth.child.header.capsule_id = id;
+            // Fit was checked when the child was built
+            integrity::seal(&mut birth.child).expect("child fits its frame");
+            let born = if birth.spawned {
+                VmEvent::Spawned {
+                    parent: birth.parent_id,
+                    child: id,
+                    at: cell,
+                }
+            } else {
+                VmEvent::Replicated {
+                    parent: birth.parent_id,
+                    child: id,
+                    at: cell,
+                }
+            };
+            let occupant = match self.config.occupancy {
+                Occupancy::Exclusive => self.index.get(cell),
+                Occupancy::Stacked => None,
+            };
+            let child_id = birth.child.header.capsule_id;
+            let placed = match (occupant, self.config.repl_policy) {
+                (None, _) => {
+                    self.place(birth.child);
+                    self.events.push(born);
+                    true
+                }
+                (Some(slot), ReplPolicy::Overwrite) => {
+                    let old = std::mem::replace(&mut self.next_queue[slot], birth.child);
+                    self.pending_moves.retain(|m| m.slot != slot);
+                    self.events.push(VmEvent::Voided {
+                        capsule_id: old.header.capsule_id,
+                        at: cell,
+                    });
+                    self.events.push(born);
+                    true
+                }
+                (Some(_), ReplPolicy::Fail | ReplPolicy::Skip) => {
+                    self.events.push(VmEvent::BirthBlocked {
+                        parent: birth.parent_id,
+                        child: child_id,
+                        at: cell,
+                    });
+                    false
+                }
+            };
+            let Some(parent) = birth.parent else {
+                continue;
+            };
+            // The overwritten occupant may have been the parent itself
+            if self.next_queue[parent].header.capsule_id != birth.parent_id {
+                continue;
+            }
+            self.set_blocked(parent, !placed);
+            if !placed && self.config.repl_policy == ReplPolicy::Fail {
+                self.trap_at(parent, birth.at, Trap::CellOccupied { target: cell });
+            }
+        }
+    }
+
+    fn set_blocked(&mut self, slot: usize, blocked: bool) {
+        let flags = &mut self.next_queue[slot].state.flags;
+        if blocked {
+            *flags |= FLAG_BLOCKED;
+        } else {
+            *flags &= !FLAG_BLOCKED;
+        }
+    }
+
+    // Raises a trap decided at the cycle boundary at the instruction `at`
+    // that caused it, as if it had trapped when it ran.
+    fn trap_at(&mut self, slot: usize, at: u32, trap: Trap) {
+        let capsule = &mut self.next_queue[slot];
+        capsule.state.ip = at;
+        let mut effects = Vec::new();
+        Self::trap(capsule, trap, &mut effects);
+        self.apply(effects, &mut Vec::new());
+    }
+
+    // Carries out this cycle's MOVEs in execution order, each against the
+    // layout the earlier ones left. Moves into an occupied cell follow the
+    // move policy.
+    fn flush_moves(&mut self) {
+        let moves = std::mem::take(&mut self.pending_moves);
+        if moves.is_empty() {
+            return;
+        }
+        // Every capsule in each cell, in queue order
+        let mut cells: HashMap<Coord, Vec<usize>> = HashMap::new();
+        for (slot, capsule) in self.next_queue.iter().enumerate() {
+            cells.entry(capsule.coord()).or_default().push(slot);
+        }
+        // Capsules that already moved this cycle; a swap never pushes them on
+        let mut arrived: HashSet<usize> = HashSet::new();
+
+        for m in moves {
+            if arrived.contains(&m.slot) {
+                // A swap already moved it; its own MOVE was from the old cell
+                continue;
+            }
+            let mover = &self.next_queue[m.slot];
+            let (capsule_id, from) = (mover.header.capsule_id, mover.coord());
+            let occupant = match self.config.occupancy {
+                Occupancy::Exclusive => cells
+                    .get(&m.target)
+                    .and_then(|slots| slots.first().copied()),
+                Occupancy::Stacked => None,
+            };
+            let swap = self.config.move_policy == MovePolicy::Swap
+                && occupant.is_some_and(|other| !arrived.contains(&other));
+            self.set_blocked(m.slot, occupant.is_some() && !swap);
+            match (occupant, self.config.move_policy) {
+                (None, _) => {
+                    self.relocate(&mut cells, m.slot, m.target);
+                    arrived.insert(m.slot);
+                    self.events.push(VmEvent::Moved {
+                        capsule_id,
+                        from,
+                        to: m.target,
+                    });
+                }
+                (Some(other), MovePolicy::Swap) if swap => {
+                    self.relocate(&mut cells, m.slot, m.target);
+                    self.relocate(&mut cells, other, from);
+                    arrived.extend([m.slot, other]);
+                    self.events.push(VmEvent::Moved {
+                        capsule_id,
+                        from,
+                        to: m.target,
+                    });
+                    self.events.push(VmEvent::Moved {
+                        capsule_id: self.next_queue[other].header.capsule_id,
+                        from: m.target,
+                        to: from,
+                    });
+                }
+                (Some(_), MovePolicy::Block | MovePolicy::Swap) => {
+                    self.events.push(VmEvent::MoveBlocked {
+                        capsule_id,
+                        at: from,
+                        target: m.target,
+                    })
+                }
+                (Some(_), MovePolicy::Trap) => {
+                    self.trap_at(m.slot, m.at, Trap::MoveBlocked { target: m.target })
+                }
+            }
+        }
+        self.index.rebuild(&self.next_queue);
+    }
+
+    fn relocate(&mut self, cells: &mut HashMap<Coord, Vec<usize>>, slot: usize, to: Coord) {
+        let capsule = &mut self.next_queue[slot];
+        if let Some(slots) = cells.get_mut(&capsule.coord()) {
+            slots.retain(|&s| s != slot);
+        }
+        let slots = cells.entry(to).or_default();
+        slots.insert(slots.partition_point(|&s| s < slot), slot);
+        (
+            capsule.header.coord_x,
+            capsule.header.coord_y,
+            capsule.header.coord_z,
+        ) = to;
+    }
+
+    // Delivers this cycle's SENDs in execution order. Messages to an empty
+    // cell or a full mailbox are dropped; either way the event says so.
+    fn flush_messages(&mut self) {
+        for message in std::mem::take(&mut self.pending_messages) {
+            let delivered = match self.index.get(message.target) {
+                Some(slot) => {
+                    let mailbox = &mut self.next_queue[slot].state.mailbox;
+                    let room = mailbox.len() < MAILBOX_CAPACITY;
+                    if room {
+                        mailbox.push_back(Message {
+                            from: message.from,
+                            value: message.value,
+                        });
+                    }
+                    room
+                }
+                None => false,
+            };
+            self.events.push(VmEvent::Sent {
+                from: message.from,
+                target: message.target,
+                value: message.value,
+                delivered,
+            });
+        }
+    }
 
-        let mut birth_queue: Vec<Capsule> = Vec::new();
-        let snapshot = self.active_queue.clone();
+    // Wakes the dormant capsules activated this cycle so they fire next cycle.
+    // Activating an empty cell or a capsule that is not dormant does nothing.
+    fn flush_activations(&mut self) {
+        for target in std::mem::take(&mut self.pending_activations) {
+            if let Some(slot) = self.index.get(target) {
+                let state = &mut self.next_queue[slot].state;
+                if state.status == CapsuleStatus::Dormant {
+                    state.status = CapsuleStatus::Running;
+                }
+            }
+        }
+    }
 
-        for i in 0..self.active_queue.len() {
-            let mut capsule = self.active_queue[i].clone();
-            self.step_capsule(&mut capsule, &snapshot, &mut birth_queue);
-            if capsule.header.capsule_id != 0 {
-                self.next_queue.push(capsule);
+    // Resolves this cycle's STOREs per the write policy, then applies them.
+    // Nothing is written if a fail-closed conflict is found.
+    fn flush_writes(&mut self) -> Result<(), VmError> {
+        let writes = std::mem::take(&mut self.pending_writes);
+        let mut order: Vec<(Coord, usize)> = Vec::new();
+        let mut resolved: HashMap<(Coord, usize), PendingWrite> = HashMap::new();
+        let mut overflowing = Vec::new();
+        // Writes each writer has made so far, and the writers that trap at
+        // one of them: what they wrote after it never happened
+        let mut made: HashMap<u32, usize> = HashMap::new();
+        let mut trapped: HashSet<u32> = HashSet::new();
+
+        // pending_writes is in execution order, so the first entry seen for a
+        // byte is the first writer.
+        for write in writes {
+            if trapped.contains(&write.writer) {
+                continue;
+            }
+            let count = made.entry(write.writer).or_default();
+            let earlier = *count;
+            *count += 1;
+            // Writes that would grow the target past its frame never land,
+            // so they take no part in conflicts either. Local writes were
+            // checked when they ran.
+            if let Some(slot) = self.index.get(write.target).filter(|_| !write.local) {
+                if !Self::fits(&self.next_queue[slot], write.idx) {
+                    // Unless the writer left or a child overwrote it
+                    let writer = write
+                        .slot
+                        .filter(|&s| self.next_queue[s].header.capsule_id == write.writer);
+                    if writer.is_some() {
+                        trapped.insert(write.writer);
+                    }
+                    overflowing.push((write, writer, earlier));
+                    continue;
+                }
+            }
+            let key = (write.target, write.idx);
+            match resolved.entry(key) {
+                Entry::Vacant(slot) => {
+                    order.push(key);
+                    slot.insert(write);
+                }
+                Entry::Occupied(mut slot) => {
+                    let current = slot.get_mut();
+                    if current.writer == write.writer {
+                        current.value = write.value;
+                        current.local &= write.local;
+                    } else if current.value != write.value {
+                        match self.config.write_policy {
+                            WritePolicy::FailClosed => {
+                                return Err(VmError::WriteConflict {
+                                    target: write.target,
+                                    idx: write.idx,
+                                    first_writer: current.writer,
+                                    second_writer: write.writer,
+                                });
+                            }
+                            WritePolicy::Merge => {
+                                // The merged byte is new to the cell too
+                                current.value |= write.value;
+                                current.local = false;
+                            }
+                            WritePolicy::FirstWriter => {}
+                        }
+                    }
+                }
             }
         }
 
-        while let Some((tx, ty, tz, idx, val)) = self.pending_writes.pop() {
-            if let Some(target) = self.next_queue.iter_mut().find(|c| {
-                c.header.coord_x == tx && c.header.coord_y == ty && c.header.coord_z == tz
-            }) {
-                if target.payload.len() <= idx {
-                    target.payload.resize(idx + 1, 0);
+        // Before anything lands, so writes from others into a rewound
+        // writer's cell are kept
+        for &(write, writer, earlier) in &overflowing {
+            if let Some(slot) = writer {
+                self.rewind(&write, slot, earlier);
+            }
+        }
+
+        for key in order {
+            let write = resolved[&key];
+            // Already in the writer's payload, and reports no event
+            if write.local {
+                continue;
+            }
+            // Writes to empty cells have no target and are dropped; the
+            // event says so.
+            let applied = match self.index.get(write.target) {
+                Some(slot) => {
+                    let target = &mut self.next_queue[slot];
+                    if target.payload.len() <= write.idx {
+                        target.payload.resize(write.idx + 1, 0);
+                    }
+                    target.payload[write.idx] = write.value;
+                    true
                 }
-                target.payload[idx] = val;
+                None => false,
+            };
+            self.events.push(VmEvent::Wrote {
+                writer: write.writer,
+                target: write.target,
+                idx: write.idx,
+                value: write.value,
+                applied,
+            });
+        }
+
+        // Dropped, and the writer traps as it would on a local STORE
+        for (write, writer, _) in overflowing {
+            self.events.push(VmEvent::Wrote {
+                writer: write.writer,
+                target: write.target,
+                idx: write.idx,
+                value: write.value,
+                applied: false,
+            });
+            if let Some(slot) = writer {
+                self.trap_at(slot, write.at, Trap::CapacityExceeded);
             }
         }
-        self.next_queue.append(&mut birth_queue);
+        Ok(())
     }
 
+    // Puts the writer in `slot` back in front of the STORE that made `write`,
+    // its write number `earlier` (from 0) this cycle, by re-running it from
+    // the start of the cycle. Its state and payload are undone to that point,
+    // and so are its later writes and messages; births, moves and events
+    // from after it have already happened and stand.
+    fn rewind(&mut self, write: &PendingWrite, slot: usize, earlier: usize) {
+        let snapshot_index = SpatialIndex::build(&self.active_queue);
+        let mut capsule = self.active_queue[write.source].clone();
+        let mut effects = Vec::new();
+        let budget = self.config.instruction_budget;
+        self.step_capsule(
+            &mut capsule,
+            budget,
+            &snapshot_index,
+            &mut effects,
+            Some(earlier),
+        );
+
+        let sent = effects
+            .iter()
+            .filter(|e| matches!(e, Effect::Send(_)))
+            .count();
+        let mut kept = 0;
+        self.pending_messages.retain(|m| {
+            if m.from != write.writer {
+                return true;
+            }
+            kept += 1;
+            kept <= sent
+        });
+        let writer = &mut self.next_queue[slot];
+        writer.state = capsule.state;
+        writer.payload = capsule.payload;
+    }
+
+    // Whether payload byte `idx` exists or can be added without growing the
+    // capsule past its frame
+    fn fits(capsule: &Capsule, idx: usize) -> bool {
+        idx < capsule.payload.len()
+            || HEADER_LEN + capsule.policy_core.len() + idx < capsule.capacity() as usize
+    }
+
+    // Fires one capsule for up to `budget` instructions and returns how many
+    // it executed. With `stop_at_write` set to n it also stops in front of a
+    // STORE once n writes have been made (see rewind).
     fn step_capsule(
-        &mut self,
+        &self,
         capsule: &mut Capsule,
-        snapshot: &Vec<Capsule>,
-        birth_queue: &mut Vec<Capsule>,
-    ) {
-        if capsule.header.capsule_id == 5 {
-            if !capsule.payload.is_empty() {
-                if let Ok(msg) = String::from_utf8(capsule.payload.clone()) {
-                    let response: String = msg.chars().rev().collect();
-                    self.output_buffer.push(response);
+        budget: u32,
+        snapshot_index: &SpatialIndex,
+        effects: &mut Vec<Effect>,
+        stop_at_write: Option<usize>,
+    ) -> u32 {
+        // Out of gas: the capsule does not fire at all this cycle, so its
+        // WAIT and deadline are left alone too
+        if budget == 0 {
+            return 0;
+        }
+        match capsule.state.role {
+            CapsuleRole::OracleOutput => {
+                // Emits the non-NUL bytes written into it (reversed), then
+                // clears itself for the next message.
+                let text: Vec<u8> = capsule
+                    .payload
+                    .iter()
+                    .copied()
+                    .filter(|&b| b != 0)
+                    .collect();
+                if !text.is_empty() {
+                    if let Ok(msg) = String::from_utf8(text) {
+                        let response: String = msg.chars().rev().collect();
+                        effects.push(Effect::Output(response));
+                    }
+                    capsule.payload.fill(0);
                 }
-                capsule.header.capsule_id = 0;
+                return 0;
             }
-            return;
+            CapsuleRole::Device => return 0,
+            CapsuleRole::Structure | CapsuleRole::UserProgram | CapsuleRole::Kernel => {}
         }
 
-        if capsule.header.flags >= 5 && capsule.header.flags <= 7 {
-            return;
+        if capsule.state.status != CapsuleStatus::Running {
+            return 0;
+        }
+        if capsule.state.wait > 0 {
+            capsule.state.wait -= 1;
+            return 0;
+        }
+
+        // Q0-Q2 (None: the capsule carries no Policy Core). A core that does
+        // not decode fails closed.
+        let policy = if capsule.policy_core.is_empty() {
+            None
+        } else {
+            match PolicyCore::decode(&capsule.policy_core) {
+                Ok(core) => Some(core),
+                Err(_) => {
+                    // Nothing may run unchecked, fault handler included
+                    capsule.state.handler = None;
+                    let trap = Trap::PolicyViolation {
+                        operation: Operation::Compute,
+                        target: capsule.coord(),
+                    };
+                    Self::trap(capsule, trap, effects);
+                    return 0;
+                }
+            }
+        };
+        if let Some(deadline) = policy.as_ref().and_then(|p| p.deadline()) {
+            if self.cycle_count > deadline {
+                capsule.state.status = CapsuleStatus::Halted;
+                effects.push(Effect::Event(VmEvent::Expired {
+                    capsule_id: capsule.header.capsule_id,
+                    deadline,
+                }));
+                return 0;
+            }
         }
-        if capsule.header.capsule_id > 200
-            && capsule.header.capsule_id != 999
-            && capsule.payload.is_empty()
+
+        // Runs until the budget is spent or the activation ends: YIELD, WAIT,
+        // REPL, SPAWN, a MOVE to another cell, RECV on an empty mailbox, a
+        // status change (HALT, VOID, DORMANT, a trap with no handler armed),
+        // or a NUL byte / the end of the payload.
+        let policy = policy.as_ref();
+        let mut used = 0;
+        while used < budget && capsule.state.status == CapsuleStatus::Running {
+            match capsule.payload.get(capsule.state.ip as usize) {
+                None | Some(0) => break,
+                Some(_) => {}
+            }
+            if stop_at_write
+                .is_some_and(|n| self.writes_next(capsule, policy, snapshot_index, n, effects))
+            {
+                break;
+            }
+            used += 1;
+            if !self.execute(capsule, policy, snapshot_index, effects) {
+                break;
+            }
+        }
+        used
+    }
+
+    // Whether, with `made` writes in `effects`, the instruction at ip makes
+    // one more. Only STOREs can, and not all of them do (they may trap).
+    fn writes_next(
+        &self,
+        capsule: &Capsule,
+        policy: Option<&PolicyCore>,
+        snapshot_index: &SpatialIndex,
+        made: usize,
+        effects: &[Effect],
+    ) -> bool {
+        let is_write = |e: &Effect| matches!(e, Effect::Write(_));
+        if capsule.payload[capsule.state.ip as usize] != OpCode::STORE as u8
+            || effects.iter().filter(|e| is_write(e)).count() != made
         {
-            return;
+            return false;
         }
+        let mut attempt = capsule.clone();
+        let mut more = Vec::new();
+        self.execute(&mut attempt, policy, snapshot_index, &mut more);
+        more.iter().any(is_write)
+    }
 
-        let mut ip = capsule.header.pad_len as usize;
+    // Executes the instruction at ip, which must be a non-NUL byte. Returns
+    // false when it ends the activation.
+    fn execute(
+        &self,
+        capsule: &mut Capsule,
+        policy: Option<&PolicyCore>,
+        snapshot_index: &SpatialIndex,
+        effects: &mut Vec<Effect>,
+    ) -> bool {
+        let at = capsule.state.ip as usize;
+        let op_byte = capsule.payload[at];
+        let Some(op) = OpCode::from_u8(op_byte) else {
+            return Self::trap(capsule, Trap::IllegalOpcode { byte: op_byte }, effects);
+        };
+        let mut ip = at + 1;
+        if ip + op.operand_len() > capsule.payload.len() {
+            return Self::trap(capsule, Trap::OperandUnderflow { opcode: op }, effects);
+        }
+        let mut continues = true;
 
-        if ip < capsule.payload.len() {
-            let op_byte = capsule.payload[ip];
-            if op_byte == 0 {
-                return;
+        // SCOPE_FILTER / HARD_CONSTRAINTS check before anything happens
+        if let Some((operation, data, target)) = Self::classify(op, capsule, ip) {
+            let allowed = match policy {
+                Some(core) => {
+                    let within_limit =
+                        !matches!(operation, Operation::Replicate | Operation::Spawn)
+                            || core
+                                .max_replications()
+                                .is_none_or(|max| (capsule.state.replications as u64) < max);
+                    within_limit && core.permits(operation, data, capsule.coord(), target)
+                }
+                None => !self.config.require_policy_core,
+            };
+            if !allowed {
+                let trap = Trap::PolicyViolation { operation, target };
+                return Self::trap(capsule, trap, effects);
             }
+        }
 
-            ip += 1;
+        match op {
+            OpCode::NOOP => {}
+            OpCode::YIELD => continues = false,
+            OpCode::HALT => capsule.state.status = CapsuleStatus::Halted,
+            OpCode::ADD => {
+                capsule.state.registers[0] =
+                    capsule.state.registers[0].wrapping_add(capsule.state.registers[1])
+            }
+            OpCode::SUB => {
+                capsule.state.registers[0] =
+                    capsule.state.registers[0].wrapping_sub(capsule.state.registers[1])
+            }
+            OpCode::INC => capsule.state.registers[0] = capsule.state.registers[0].wrapping_add(1),
+            OpCode::DEC => capsule.state.registers[0] = capsule.state.registers[0].wrapping_sub(1),
+            OpCode::LOG => {
+                effects.push(Effect::Event(VmEvent::Logged {
+                    capsule_id: capsule.header.capsule_id,
+                    value: capsule.state.registers[0],
+                }));
+            }
 
-            if let Some(op) = OpCode::from_u8(op_byte) {
-                match op {
-                    OpCode::NOOP => {}
-                    OpCode::HALT => {
-                        capsule.payload.clear();
-                        ip = 0;
-                    }
-                    OpCode::ADD => {
-                        self.registers[0] = self.registers[0].wrapping_add(self.registers[1])
+            // R0 is the accumulator; GET/PUT move values between it and R0-R3.
+            OpCode::GET => {
+                let reg = capsule.payload[ip] as usize;
+                ip += 1;
+                if reg < capsule.state.registers.len() {
+                    capsule.state.registers[0] = capsule.state.registers[reg];
+                }
+            }
+            OpCode::PUT => {
+                let reg = capsule.payload[ip] as usize;
+                ip += 1;
+                if reg < capsule.state.registers.len() {
+                    capsule.state.registers[reg] = capsule.state.registers[0];
+                }
+            }
+
+            // OP dst src: dst = dst OP src. Like GET and PUT, a register
+            // operand outside R0-R3 makes the instruction a no-op.
+            OpCode::MOV
+            | OpCode::MUL
+            | OpCode::DIV
+            | OpCode::MOD
+            | OpCode::AND
+            | OpCode::OR
+            | OpCode::XOR
+            | OpCode::SHL
+            | OpCode::SHR => {
+                let dst = capsule.payload[ip] as usize;
+                let src = capsule.payload[ip + 1] as usize;
+                ip += 2;
+                let regs = &mut capsule.state.registers;
+                if dst < regs.len() && src < regs.len() {
+                    match Self::alu(op, regs[dst], regs[src]) {
+                        Some(value) => regs[dst] = value,
+                        None => return Self::trap(capsule, Trap::DivideByZero, effects),
                     }
-                    OpCode::SUB => {
-                        self.registers[0] = self.registers[0].wrapping_sub(self.registers[1])
+                }
+            }
+
+            // LDI reg imm: the immediate is a signed byte, as BASM writes it
+            OpCode::LDI => {
+                let reg = capsule.payload[ip] as usize;
+                let imm = capsule.payload[ip + 1] as i8;
+                ip += 2;
+                if reg < capsule.state.registers.len() {
+                    capsule.state.registers[reg] = imm as i32;
+                }
+            }
+
+            OpCode::CMP => {
+                let a = capsule.payload[ip] as usize;
+                let b = capsule.payload[ip + 1] as usize;
+                ip += 2;
+                let regs = &capsule.state.registers;
+                if a < regs.len() && b < regs.len() {
+                    let order = match regs[a].cmp(&regs[b]) {
+                        Ordering::Equal => FLAG_EQUAL,
+                        Ordering::Less => FLAG_LESS,
+                        Ordering::Greater => 0,
+                    };
+                    capsule.state.flags = capsule.state.flags & FLAG_BLOCKED | order;
+                }
+            }
+
+            OpCode::STORE => {
+                let dx = capsule.payload[ip] as i8;
+                let dy = capsule.payload[ip + 1] as i8;
+                let dz = capsule.payload[ip + 2] as i8;
+                let idx = capsule.payload[ip + 3] as usize;
+                let target = Self::offset(capsule, dx, dy, dz);
+                ip += 4;
+
+                let val = (capsule.state.registers[0] & 0xFF) as u8;
+
+                // [FIX 1: IMMEDIATE LOCAL WRITE]
+                // Applied now, not at the boundary: by then the writer may
+                // have moved, and the cell may hold someone else. Still
+                // recorded, so a remote write to the same byte conflicts.
+                let local = dx == 0 && dy == 0 && dz == 0;
+                if local {
+                    if !Self::fits(capsule, idx) {
+                        return Self::trap(capsule, Trap::CapacityExceeded, effects);
                     }
-                    OpCode::INC => self.registers[0] = self.registers[0].wrapping_add(1),
-                    OpCode::DEC => self.registers[0] = self.registers[0].wrapping_sub(1),
-                    OpCode::LOG => {
-                        println!(
-                            "VM [Cycle {}]: R0 = {}",
-                            self.cycle_count, self.registers[0]
-                        );
+                    if capsule.payload.len() <= idx {
+                        capsule.payload.resize(idx + 1, 0);
                     }
+                    capsule.payload[idx] = val;
+                }
+                effects.push(Effect::Write(PendingWrite {
+                    writer: capsule.header.capsule_id,
+                    // Both known once the writer is placed
+                    source: 0,
+                    slot: None,
+                    target,
+                    idx,
+                    value: val,
+                    at: at as u32,
+                    local,
+                }));
+            }
 
-                    OpCode::STORE => {
-                        if ip + 4 <= capsule.payload.len() {
-                            let dx = capsule.payload[ip] as i8;
-                            let dy = capsule.payload[ip + 1] as i8;
-                            let dz = capsule.payload[ip + 2] as i8;
-                            let idx = capsule.payload[ip + 3] as usize;
-                            ip += 4;
-
-                            let tx = capsule.header.coord_x + dx as i16;
-                            let ty = capsule.header.coord_y + dy as i16;
-                            let tz = capsule.header.coord_z + dz as i16;
-                            let val = (self.registers[0] & 0xFF) as u8;
-
-                            // [FIX 1: IMMEDIATE LOCAL WRITE]
-                            if dx == 0 && dy == 0 && dz == 0 {
-                                if capsule.payload.len() <= idx {
-                                    capsule.payload.resize(idx + 1, 0);
-                                }
-                                capsule.payload[idx] = val;
-                            }
-                            self.pending_writes.push((tx, ty, tz, idx, val));
+            OpCode::LOAD => {
+                let dx = capsule.payload[ip] as i8;
+                let dy = capsule.payload[ip + 1] as i8;
+                let dz = capsule.payload[ip + 2] as i8;
+                let idx = capsule.payload[ip + 3] as usize;
+                ip += 4;
+
+                // [FIX 2: READ SELF DIRECTLY]
+                if dx == 0 && dy == 0 && dz == 0 {
+                    if idx < capsule.payload.len() {
+                        capsule.state.registers[0] = capsule.payload[idx] as i32;
+                    } else {
+                        capsule.state.registers[0] = 0;
+                    }
+                } else {
+                    let target = Self::offset(capsule, dx, dy, dz);
+                    if let Some(slot) = snapshot_index.get(target) {
+                        let target = &self.active_queue[slot];
+                        if idx < target.payload.len() {
+                            capsule.state.registers[0] = target.payload[idx] as i32;
+                        } else {
+                            capsule.state.registers[0] = 0;
                         }
                     }
+                }
+            }
 
-                    OpCode::LOAD => {
-                        if ip + 4 <= capsule.payload.len() {
-                            let dx = capsule.payload[ip] as i8;
-                            let dy = capsule.payload[ip + 1] as i8;
-                            let dz = capsule.payload[ip + 2] as i8;
-                            let idx = capsule.payload[ip + 3] as usize;
-                            ip += 4;
-
-                            // [FIX 2: READ SELF DIRECTLY]
-                            if dx == 0 && dy == 0 && dz == 0 {
-                                if idx < capsule.payload.len() {
-                                    self.registers[0] = capsule.payload[idx] as i32;
-                                } else {
-                                    self.registers[0] = 0;
-                                }
-                            } else {
-                                let tx = capsule.header.coord_x + dx as i16;
-                                let ty = capsule.header.coord_y + dy as i16;
-                                let tz = capsule.header.coord_z + dz as i16;
-
-                                if let Some(target) = snapshot.iter().find(|c| {
-                                    c.header.coord_x == tx
-                                        && c.header.coord_y == ty
-                                        && c.header.coord_z == tz
-                                }) {
-                                    if idx < target.payload.len() {
-                                        self.registers[0] = target.payload[idx] as i32;
-                                    } else {
-                                        self.registers[0] = 0;
-                                    }
-                                }
-                            }
-                        }
+            OpCode::JMP => {
+                let target_idx = capsule.payload[ip] as usize;
+                if target_idx >= capsule.payload.len() {
+                    let trap = Trap::JumpOutOfRange {
+                        target: target_idx as u32,
+                    };
+                    return Self::trap(capsule, trap, effects);
+                }
+                ip = target_idx;
+            }
+
+            // CALL target: pushes the address after the operand, then jumps
+            OpCode::CALL => {
+                let target_idx = capsule.payload[ip] as usize;
+                ip += 1;
+                if target_idx >= capsule.payload.len() {
+                    let trap = Trap::JumpOutOfRange {
+                        target: target_idx as u32,
+                    };
+                    return Self::trap(capsule, trap, effects);
+                }
+                if capsule.state.call_stack.len() >= CALL_STACK_DEPTH {
+                    return Self::trap(capsule, Trap::StackOverflow, effects);
+                }
+                capsule.state.call_stack.push(ip as u32);
+                ip = target_idx;
+            }
+
+            OpCode::RET => match capsule.state.call_stack.pop() {
+                Some(ret) => ip = ret as usize,
+                None => return Self::trap(capsule, Trap::StackUnderflow, effects),
+            },
+
+            // Bcc val target: R0 against the literal, signed like BASM
+            // writes it
+            OpCode::BEQ | OpCode::BNE | OpCode::BLT | OpCode::BGT | OpCode::BLE | OpCode::BGE => {
+                let check_val = capsule.payload[ip] as i8 as i32;
+                let target_idx = capsule.payload[ip + 1] as usize;
+                ip += 2;
+                if Self::branch_taken(op, capsule.state.registers[0].cmp(&check_val)) {
+                    if target_idx >= capsule.payload.len() {
+                        let trap = Trap::JumpOutOfRange {
+                            target: target_idx as u32,
+                        };
+                        return Self::trap(capsule, trap, effects);
                     }
+                    ip = target_idx;
+                }
+            }
 
-                    OpCode::JMP => {
-                        if ip < capsule.payload.len() {
-                            let target_idx = capsule.payload[ip] as usize;
-                            ip = target_idx;
-                        }
+            // BccF target: the outcome of the last CMP a b
+            OpCode::BEQF
+            | OpCode::BNEF
+            | OpCode::BLTF
+            | OpCode::BGTF
+            | OpCode::BLEF
+            | OpCode::BGEF => {
+                let target_idx = capsule.payload[ip] as usize;
+                ip += 1;
+                let order = match capsule.state.flags & (FLAG_EQUAL | FLAG_LESS) {
+                    FLAG_EQUAL => Ordering::Equal,
+                    FLAG_LESS => Ordering::Less,
+                    _ => Ordering::Greater,
+                };
+                if Self::branch_taken(op, order) {
+                    if target_idx >= capsule.payload.len() {
+                        let trap = Trap::JumpOutOfRange {
+                            target: target_idx as u32,
+                        };
+                        return Self::trap(capsule, trap, effects);
                     }
+                    ip = target_idx;
+                }
+            }
 
-                    OpCode::BEQ => {
-                        if ip + 2 <= capsule.payload.len() {
-                            let check_val = capsule.payload[ip] as i32;
-                            let target_idx = capsule.payload[ip + 1] as usize;
-                            ip += 2;
-                            if self.registers[0] == check_val {
-                                ip = target_idx;
-                            }
-                        }
+            // BBLK target: taken if the last REPL, SPAWN or MOVE found its
+            // cell taken (FLAG_BLOCKED, set at the cycle boundary)
+            OpCode::BBLK => {
+                let target_idx = capsule.payload[ip] as usize;
+                ip += 1;
+                if capsule.state.flags & FLAG_BLOCKED != 0 {
+                    if target_idx >= capsule.payload.len() {
+                        let trap = Trap::JumpOutOfRange {
+                            target: target_idx as u32,
+                        };
+                        return Self::trap(capsule, trap, effects);
                     }
+                    ip = target_idx;
+                }
+            }
+
+            // Sensing reads this cycle's snapshot, like LOAD, so what a capsule
+            // sees does not depend on who ran before it.
+            OpCode::GETX => capsule.state.registers[0] = capsule.header.coord_x as i32,
+            OpCode::GETY => capsule.state.registers[0] = capsule.header.coord_y as i32,
+            OpCode::GETZ => capsule.state.registers[0] = capsule.header.coord_z as i32,
+            OpCode::GETID => capsule.state.registers[0] = capsule.header.capsule_id as i32,
+            // Low 32 bits of the cycle being executed
+            OpCode::GETCYC => capsule.state.registers[0] = self.cycle_count as i32,
+
+            OpCode::SENSE => {
+                if let Some(target) = Self::relative_target(capsule, ip) {
+                    ip += 3;
+                    capsule.state.registers[0] = snapshot_index.get(target).is_some() as i32;
+                }
+            }
 
-                    OpCode::REPL => {
-                        if ip + 3 <= capsule.payload.len() {
-                            let dx = capsule.payload[ip] as i8;
-                            let dy = capsule.payload[ip + 1] as i8;
-                            let dz = capsule.payload[ip + 2] as i8;
-                            ip += 3;
-
-                            let tx = capsule.header.coord_x + dx as i16;
-                            let ty = capsule.header.coord_y + dy as i16;
-                            let tz = capsule.header.coord_z + dz as i16;
-
-                            let mut clone = capsule.clone();
-                            clone.header.coord_x = tx;
-                            clone.header.coord_y = ty;
-                            clone.header.coord_z = tz;
-                            clone.header.capsule_id = self.next_id;
-                            self.next_id += 1;
-                            clone.header.pad_len = 0;
-                            clone.payload = capsule.payload.clone();
-
-                            birth_queue.push(clone);
-                            println!("VM [REPL]: Replicated to ({},{},{})", tx, ty, tz);
+            // COUNTN 0: the 6 face neighbours (von Neumann); any other
+            // operand: all 26 surrounding cells (Moore)
+            OpCode::COUNTN => {
+                let moore = capsule.payload[ip] != 0;
+                ip += 1;
+                let (x, y, z) = capsule.coord();
+                let mut count = 0;
+                for dx in -1i16..=1 {
+                    for dy in -1i16..=1 {
+                        for dz in -1i16..=1 {
+                            let distance = dx.abs() + dy.abs() + dz.abs();
+                            if distance == 0 || (!moore && distance > 1) {
+                                continue;
+                            }
+                            let cell = (x.wrapping_add(dx), y.wrapping_add(dy), z.wrapping_add(dz));
+                            if snapshot_index.get(cell).is_some() {
+                                count += 1;
+                            }
                         }
                     }
+                }
+                capsule.state.registers[0] = count;
+            }
 
-                    OpCode::VOID => {
-                        capsule.header.capsule_id = 0;
+            // MOVE dx dy dz: relocates this capsule with its id and state. The
+            // move is carried out at the cycle boundary, so it ends the
+            // activation; MOVE 0 0 0 does nothing.
+            OpCode::MOVE => {
+                if let Some(target) = Self::relative_target(capsule, ip) {
+                    ip += 3;
+                    if target != capsule.coord() {
+                        effects.push(Effect::Move {
+                            target,
+                            at: at as u32,
+                        });
+                        continues = false;
                     }
+                }
+            }
+
+            // SEND dx dy dz: R0 to the mailbox of the capsule at the offset
+            OpCode::SEND => {
+                if let Some(target) = Self::relative_target(capsule, ip) {
+                    ip += 3;
+                    effects.push(Effect::Send(PendingMessage {
+                        from: capsule.header.capsule_id,
+                        target,
+                        value: capsule.state.registers[0],
+                    }));
+                }
+            }
+
+            // Takes the oldest message: R0 = value, R1 = sender id. Blocks on
+            // an empty mailbox by ending the activation and retrying next cycle.
+            OpCode::RECV => match capsule.state.mailbox.pop_front() {
+                Some(message) => {
+                    capsule.state.registers[0] = message.value;
+                    capsule.state.registers[1] = message.from as i32;
+                }
+                None => {
+                    ip = at;
+                    continues = false;
+                }
+            },
 
-                    OpCode::SPAWN => {}
+            // R0 = messages waiting, without taking any
+            OpCode::POLL => capsule.state.registers[0] = capsule.state.mailbox.len() as i32,
+
+            // Arms the fault handler: the next trap jumps there instead of
+            // faulting the capsule.
+            OpCode::ONFAULT => {
+                let handler = capsule.payload[ip] as u32;
+                ip += 1;
+                if handler as usize >= capsule.payload.len() {
+                    let trap = Trap::JumpOutOfRange { target: handler };
+                    return Self::trap(capsule, trap, effects);
                 }
+                capsule.state.handler = Some(handler);
+            }
+
+            OpCode::REPL => {
+                let dx = capsule.payload[ip] as i8;
+                let dy = capsule.payload[ip + 1] as i8;
+                let dz = capsule.payload[ip + 2] as i8;
+                ip += 3;
+
+                let (tx, ty, tz) = Self::offset(capsule, dx, dy, dz);
+                let mut clone = capsule.clone();
+                clone.header.coord_x = tx;
+                clone.header.coord_y = ty;
+                clone.header.coord_z = tz;
+                clone.state.ip = 0;
+                clone.state.replications = 0;
+                clone.state.mailbox.clear();
+                clone.state.call_stack.clear();
+                // Sealed at the boundary, which needs the lengths to fit
+                if LatticeCodec::fill_lengths(&mut clone).is_err() {
+                    return Self::trap(capsule, Trap::CapacityExceeded, effects);
+                }
+                capsule.state.replications += 1;
+
+                effects.push(Effect::Birth {
+                    parent: capsule.header.capsule_id,
+                    child: Box::new(clone),
+                    spawned: false,
+                    at: at as u32,
+                });
+                // Placed at the boundary, where it may still trap
+                continues = false;
+            }
+
+            OpCode::VOID => capsule.state.status = CapsuleStatus::Voided,
+
+            OpCode::ACTIVATE => {
+                if let Some(target) = Self::absolute_target(capsule, ip) {
+                    ip += 6;
+                    effects.push(Effect::Activate(target));
+                }
+            }
+
+            OpCode::ACTIVATE_NEIGHBOR => {
+                if let Some(target) = Self::relative_target(capsule, ip) {
+                    ip += 3;
+                    effects.push(Effect::Activate(target));
+                }
+            }
+
+            // Sleeps and rewinds, so the next activation runs the
+            // program from the top.
+            OpCode::DORMANT => {
+                capsule.state.status = CapsuleStatus::Dormant;
+                capsule.state.call_stack.clear();
+                ip = 0;
+            }
+
+            OpCode::WAIT => {
+                capsule.state.wait = capsule.payload[ip] as u32;
+                ip += 1;
+                continues = false;
+            }
+
+            // SPAWN dx dy dz start len: a new capsule running
+            // payload[start..start+len] of this one, or a blank
+            // structure brick when len is 0.
+            OpCode::SPAWN => {
+                let dx = capsule.payload[ip] as i8;
+                let dy = capsule.payload[ip + 1] as i8;
+                let dz = capsule.payload[ip + 2] as i8;
+                let start = capsule.payload[ip + 3] as usize;
+                let len = capsule.payload[ip + 4] as usize;
+                ip += 5;
+
+                let cell = Self::offset(capsule, dx, dy, dz);
+                let child = if len == 0 {
+                    Self::brick(capsule, cell)
+                } else {
+                    let Some(code) = capsule.payload.get(start..start + len) else {
+                        let trap = Trap::SliceOutOfRange {
+                            start: start as u32,
+                            len: len as u32,
+                        };
+                        return Self::trap(capsule, trap, effects);
+                    };
+                    Self::offspring(capsule, cell, code.to_vec())
+                };
+
+                let Some(child) = child else {
+                    return Self::trap(capsule, Trap::CapacityExceeded, effects);
+                };
+                capsule.state.replications += 1;
+                effects.push(Effect::Birth {
+                    parent: capsule.header.capsule_id,
+                    child: Box::new(child),
+                    spawned: true,
+                    at: at as u32,
+                });
+                continues = false;
             }
         }
-        capsule.header.pad_len = ip as u32;
+        capsule.state.ip = ip as u32;
+        continues
+    }
+
+    // Raises `trap` at the current instruction. With a handler armed the
+    // capsule continues there with the trap code in R0 (the handler is
+    // disarmed, so a second trap faults); otherwise it stops as Faulted.
+    fn trap(capsule: &mut Capsule, trap: Trap, effects: &mut Vec<Effect>) -> bool {
+        let handler = capsule.state.handler.take();
+        effects.push(Effect::Event(VmEvent::Trapped {
+            capsule_id: capsule.header.capsule_id,
+            ip: capsule.state.ip,
+            trap,
+            handled: handler.is_some(),
+        }));
+        capsule.state.fault = Some(trap);
+        match handler {
+            Some(handler) => {
+                capsule.state.registers[0] = trap.code() as i32;
+                capsule.state.ip = handler;
+                true
+            }
+            None => {
+                capsule.state.status = CapsuleStatus::Faulted;
+                false
+            }
+        }
+    }
+
+    // Whether a conditional branch is taken when its left-hand side
+    // compares to the right-hand side as `order`
+    fn branch_taken(op: OpCode, order: Ordering) -> bool {
+        match op {
+            OpCode::BEQ | OpCode::BEQF => order.is_eq(),
+            OpCode::BNE | OpCode::BNEF => order.is_ne(),
+            OpCode::BLT | OpCode::BLTF => order.is_lt(),
+            OpCode::BGT | OpCode::BGTF => order.is_gt(),
+            OpCode::BLE | OpCode::BLEF => order.is_le(),
+            OpCode::BGE | OpCode::BGEF => order.is_ge(),
+            _ => unreachable!("{} is not a branch", op.mnemonic()),
+        }
+    }
+
+    // dst OP src for the two-register instructions. Wraps on overflow;
+    // None on division by zero.
+    fn alu(op: OpCode, dst: i32, src: i32) -> Option<i32> {
+        Some(match op {
+            OpCode::MOV => src,
+            OpCode::MUL => dst.wrapping_mul(src),
+            OpCode::DIV | OpCode::MOD if src == 0 => return None,
+            OpCode::DIV => dst.wrapping_div(src),
+            OpCode::MOD => dst.wrapping_rem(src),
+            OpCode::AND => dst & src,
+            OpCode::OR => dst | src,
+            OpCode::XOR => dst ^ src,
+            OpCode::SHL => dst.wrapping_shl(src as u32),
+            OpCode::SHR => dst.wrapping_shr(src as u32),
+            _ => unreachable!("{} is not a register operation", op.mnemonic()),
+        })
+    }
+
+    // Cell addressed by the dx dy dz operands at `ip`, if they are present
+    fn relative_target(capsule: &Capsule, ip: usize) -> Option<Coord> {
+        let ops = capsule.payload.get(ip..ip + 3)?;
+        Some(Self::offset(
+            capsule,
+            ops[0] as i8,
+            ops[1] as i8,
+            ops[2] as i8,
+        ))
+    }
+
+    // The cell at an offset from the capsule. Coordinates wrap at the i16
+    // edges, like COUNTN's neighbourhood, so every offset names a cell.
+    fn offset(capsule: &Capsule, dx: i8, dy: i8, dz: i8) -> Coord {
+        (
+            capsule.header.coord_x.wrapping_add(dx as i16),
+            capsule.header.coord_y.wrapping_add(dy as i16),
+            capsule.header.coord_z.wrapping_add(dz as i16),
+        )
+    }
+
+    // Cell named by x y z word operands at `ip`, if they are present
+    fn absolute_target(capsule: &Capsule, ip: usize) -> Option<Coord> {
+        let ops = capsule.payload.get(ip..ip + 6)?;
+        let word = |i: usize| i16::from_be_bytes([ops[i], ops[i + 1]]);
+        Some((word(0), word(2), word(4)))
+    }
+
+    // What an instruction touches, for the Policy Core check. None for
+    // instructions that only reduce activity (NOOP, HALT, WAIT, DORMANT).
+    fn classify(op: OpCode, capsule: &Capsule, ip: usize) -> Option<(Operation, DataClass, Coord)> {
+        let own = capsule.coord();
+        // Only for instructions whose first operands are dx dy dz
+        let remote = || Self::relative_target(capsule, ip).unwrap_or(own);
+        match op {
+            OpCode::NOOP | OpCode::HALT | OpCode::WAIT | OpCode::DORMANT | OpCode::YIELD => None,
+            OpCode::ADD
+            | OpCode::SUB
+            | OpCode::INC
+            | OpCode::DEC
+            | OpCode::GET
+            | OpCode::PUT
+            | OpCode::JMP
+            | OpCode::BEQ
+            | OpCode::BNE
+            | OpCode::BLT
+            | OpCode::BGT
+            | OpCode::BLE
+            | OpCode::BGE
+            | OpCode::BEQF
+            | OpCode::BNEF
+            | OpCode::BLTF
+            | OpCode::BGTF
+            | OpCode::BLEF
+            | OpCode::BGEF
+            | OpCode::BBLK
+            | OpCode::ONFAULT
+            | OpCode::RECV
+            | OpCode::POLL
+            | OpCode::CALL
+            | OpCode::RET
+            | OpCode::MOV
+            | OpCode::LDI
+            | OpCode::MUL
+            | OpCode::DIV
+            | OpCode::MOD
+            | OpCode::AND
+            | OpCode::OR
+            | OpCode::XOR
+            | OpCode::SHL
+            | OpCode::SHR
+            | OpCode::CMP
+            | OpCode::GETX
+            | OpCode::GETY
+            | OpCode::GETZ
+            | OpCode::GETID
+            | OpCode::GETCYC => Some((Operation::Compute, DataClass::Registers, own)),
+            OpCode::LOG => Some((Operation::Emit, DataClass::Output, own)),
+            OpCode::STORE => Some((Operation::Write, DataClass::Code, remote())),
+            OpCode::LOAD | OpCode::SENSE => Some((Operation::Read, DataClass::Code, remote())),
+            OpCode::COUNTN => Some((Operation::Read, DataClass::Code, own)),
+            OpCode::REPL => Some((Operation::Replicate, DataClass::Code, remote())),
+            OpCode::SPAWN => Some((Operation::Spawn, DataClass::Code, remote())),
+            OpCode::VOID => Some((Operation::Void, DataClass::Code, own)),
+            OpCode::ACTIVATE => {
+                let at = Self::absolute_target(capsule, ip).unwrap_or(own);
+                Some((Operation::Activate, DataClass::Code, at))
+            }
+            OpCode::ACTIVATE_NEIGHBOR => Some((Operation::Activate, DataClass::Code, remote())),
+            OpCode::SEND => Some((Operation::Message, DataClass::Registers, remote())),
+            OpCode::MOVE => Some((Operation::Move, DataClass::Code, remote())),
+        }
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted.is_some()
     }
 
     pub fn is_void(&self) -> bool {
@@ -0,0 +1,115 @@
+use crate::capsules::{Capsule, CapsuleHeader, CapsuleRole, ExecState};
+use crate::config::{Occupancy, SchedulingMode, WritePolicy};
+use crate::vm::LatticeVM;
+use serde::Deserialize;
+use std::io;
+
+// Universe snapshot files (universe_*.bin)
+// Layout: WORLD_MAGIC | WORLD_VERSION (u16, big-endian) | bincode(LatticeVM)
+// Files without the magic are pre-versioning snapshots (see LegacyWorld).
+// Bump the version, and migrate the one before it, whenever a change to
+// LatticeVM or anything it saves alters the bincode layout.
+
+pub const WORLD_MAGIC: [u8; 4] = *b"BLUV";
+pub const WORLD_VERSION: u16 = 1;
+
+pub fn encode(vm: &LatticeVM) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&WORLD_MAGIC);
+    out.extend_from_slice(&WORLD_VERSION.to_be_bytes());
+    let body = bincode::serialize(vm).map_err(io::Error::other)?;
+    out.extend_from_slice(&body);
+    Ok(out)
+}
+
+// The decoded VM comes with its coordinate index rebuilt, ready to run.
+pub fn decode(data: &[u8]) -> io::Result<LatticeVM> {
+    let mut vm = if data.len() < 6 || data[0..4] != WORLD_MAGIC {
+        let legacy: LegacyWorld = bincode::deserialize(data).map_err(io::Error::other)?;
+        legacy.migrate()
+    } else {
+        match u16::from_be_bytes([data[4], data[5]]) {
+            WORLD_VERSION => bincode::deserialize(&data[6..]).map_err(io::Error::other)?,
+            version => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unsupported universe format version {}", version),
+                ))
+            }
+        }
+    };
+    vm.rebuild_index();
+    Ok(vm)
+}
+
+// --- PRE-VERSIONING FORMAT ---
+// The VM used to share one register file across all capsules and kept each
+// capsule's instruction pointer in header.pad_len. Roles were implied by
+// magic ids and flags.
+
+#[derive(Deserialize)]
+struct LegacyCapsule {
+    header: CapsuleHeader,
+    policy_core: Vec<u8>,
+    payload: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct LegacyWorld {
+    active_queue: Vec<LegacyCapsule>,
+    next_queue: Vec<LegacyCapsule>,
+    cycle_count: u64,
+    registers: [i32; 4],
+    next_id: u32,
+    universe_id: String,
+    output_buffer: Vec<String>,
+}
+
+impl LegacyWorld {
+    fn migrate(self) -> LatticeVM {
+        let registers = self.registers;
+        let upgrade = |old: LegacyCapsule| {
+            let mut header = old.header;
+            let ip = header.pad_len;
+            header.pad_len = 0;
+            let role = legacy_role(&header);
+            Capsule {
+                header,
+                policy_core: old.policy_core,
+                payload: old.payload,
+                state: ExecState {
+                    ip,
+                    // Best effort: every capsule starts from the old shared file
+                    registers,
+                    role,
+                    ..ExecState::default()
+                },
+            }
+        };
+
+        let mut vm = LatticeVM::empty(self.universe_id);
+        vm.active_queue = self.active_queue.into_iter().map(upgrade).collect();
+        vm.next_queue = self.next_queue.into_iter().map(upgrade).collect();
+        vm.cycle_count = self.cycle_count;
+        vm.next_id = self.next_id;
+        vm.output_buffer = self.output_buffer;
+        // Reproduce the old runtime: z,y,x ordering, and the reverse-order
+        // write flush in which the first write of a cycle survived.
+        vm.config.scheduling = SchedulingMode::Legacy;
+        vm.config.write_policy = WritePolicy::FirstWriter;
+        vm.config.instruction_budget = 1;
+        vm.config.occupancy = Occupancy::Stacked;
+        vm
+    }
+}
+
+// The rules the old step function applied by id and flags
+fn legacy_role(header: &CapsuleHeader) -> CapsuleRole {
+    match (header.capsule_id, header.flags) {
+        (5, _) => CapsuleRole::OracleOutput,
+        (_, 5..=7) => CapsuleRole::Device,
+        (1, _) => CapsuleRole::Kernel,
+        (_, 2) => CapsuleRole::Structure,
+        _ => CapsuleRole::UserProgram,
+    }
+}
@@ -0,0 +1,92 @@
+use binling_core::asm::{assemble, AsmError};
+use binling_core::instructions::OpCode;
+
+#[test]
+fn signed_operands_take_minus_128_to_127() {
+    assert_eq!(
+        assemble("BGT -128 0 LDI R1 127 MOVE -1 0 1").unwrap(),
+        vec![
+            OpCode::BGT as u8,
+            0x80,
+            0,
+            OpCode::LDI as u8,
+            1,
+            127,
+            OpCode::MOVE as u8,
+            0xFF,
+            0,
+            1,
+        ]
+    );
+}
+
+#[test]
+fn signed_operand_above_127_is_rejected() {
+    // A label past 127 is no better than the number
+    let far_label = format!("{} x: MOVE 0 x 0", "NOOP ".repeat(130));
+    for (src, mnemonic, token) in [
+        ("BGT 200 0", "BGT", "200"),
+        ("BEQ 128 0", "BEQ", "128"),
+        ("LDI R0 255", "LDI", "255"),
+        ("STORE 0 0 130 4", "STORE", "130"),
+        (far_label.as_str(), "MOVE", "x"),
+    ] {
+        assert_eq!(
+            assemble(src),
+            Err(AsmError::SignedOutOfRange {
+                mnemonic: mnemonic.to_string(),
+                token: token.to_string(),
+            }),
+            "{}",
+            src
+        );
+    }
+}
+
+#[test]
+fn unsigned_operands_keep_the_full_byte() {
+    // Branch targets, STORE and SPAWN indices, WAIT counts and raw bytes
+    assert_eq!(
+        assemble("BGT 1 200 STORE 0 0 0 255 WAIT 200 250").unwrap(),
+        vec![
+            OpCode::BGT as u8,
+            1,
+            200,
+            OpCode::STORE as u8,
+            0,
+            0,
+            0,
+            255,
+            OpCode::WAIT as u8,
+            200,
+            250,
+        ]
+    );
+}
+
+#[test]
+fn activate_takes_word_coordinates() {
+    // Big-endian i16s; labels after them land past the six operand bytes
+    assert_eq!(
+        assemble("ACTIVATE 300 -200 0 end: HALT JMP end").unwrap(),
+        vec![
+            OpCode::ACTIVATE as u8,
+            0x01,
+            0x2C,
+            0xFF,
+            0x38,
+            0,
+            0,
+            OpCode::HALT as u8,
+            OpCode::JMP as u8,
+            7,
+        ]
+    );
+    assert_eq!(
+        assemble("ACTIVATE 0 32768 0"),
+        Err(AsmError::SignedOutOfRange {
+            mnemonic: "ACTIVATE".to_string(),
+            token: "32768".to_string(),
+        })
+    );
+}
@@ -0,0 +1,133 @@
+use binling_core::capsules::{Capsule, CapsuleHeader, ExecState, SquareSpace};
+use binling_core::codec::{CodecError, LatticeCodec, HEADER_LEN};
+
+// Offsets of PAD_LEN and PAYLOAD_LEN in the fixed header
+const OFF_PAD_LEN: usize = 4;
+const OFF_PAYLOAD_LEN: usize = 18;
+
+fn sample() -> Capsule {
+    let mut capsule = Capsule {
+        header: CapsuleHeader {
+            magic: *b"BLE1",
+            version_major: 0,
+            version_minor: 1,
+            flags: 0x0102,
+            capsule_id: 0xDEAD_BEEF,
+            ss_n: SquareSpace::SS8,
+            priority: 7,
+            coord_x: -3,
+            coord_y: 300,
+            coord_z: i16::MIN,
+            header_len: 0,
+            policy_len: 0,
+            payload_len: 0,
+            pad_len: 0,
+            dict_hash: [1; 32],
+            policy_core_hash: [2; 32],
+            capsule_hash: [3; 32],
+        },
+        policy_core: vec![9, 8, 7],
+        payload: vec![1, 2, 3, 4, 5],
+        state: ExecState::default(),
+    };
+    LatticeCodec::fill_lengths(&mut capsule).unwrap();
+    capsule
+}
+
+#[test]
+fn encode_then_decode_round_trips() {
+    let capsule = sample();
+    let frame = LatticeCodec::encode(&capsule).unwrap();
+    assert_eq!(frame.len(), 8 * 8 * 8);
+    assert_eq!(&frame[..4], b"BLE1");
+
+    let decoded = LatticeCodec::decode(&frame).unwrap();
+    assert_eq!(decoded.header, capsule.header);
+    assert_eq!(decoded.policy_core, capsule.policy_core);
+    assert_eq!(decoded.payload, capsule.payload);
+    assert_eq!(LatticeCodec::encode(&decoded).unwrap(), frame);
+}
+
+#[test]
+fn empty_payload_round_trips() {
+    let mut capsule = sample();
+    capsule.policy_core.clear();
+    capsule.payload.clear();
+    LatticeCodec::fill_lengths(&mut capsule).unwrap();
+    let frame = LatticeCodec::encode(&capsule).unwrap();
+    let decoded = LatticeCodec::decode(&frame).unwrap();
+    assert_eq!(decoded.header, capsule.header);
+    assert!(decoded.payload.is_empty());
+}
+
+#[test]
+fn oversized_capsule_is_not_encoded() {
+    let mut capsule = sample();
+    capsule.payload = vec![1; 512];
+    assert_eq!(
+        LatticeCodec::encode(&capsule),
+        Err(CodecError::CapacityExceeded {
+            needed: HEADER_LEN + 3 + 512,
+            capacity: 512,
+        })
+    );
+}
+
+#[test]
+fn bad_magic_is_rejected() {
+    let mut frame = LatticeCodec::encode(&sample()).unwrap();
+    frame[0] = b'X';
+    assert_eq!(
+        LatticeCodec::decode(&frame).err(),
+        Some(CodecError::BadMagic)
+    );
+}
+
+#[test]
+fn short_frame_is_rejected() {
+    let frame = LatticeCodec::encode(&sample()).unwrap();
+    assert_eq!(
+        LatticeCodec::decode(&frame[..HEADER_LEN - 1]).err(),
+        Some(CodecError::TooShort(HEADER_LEN - 1))
+    );
+}
+
+#[test]
+fn pad_len_mismatch_is_rejected() {
+    let mut frame = LatticeCodec::encode(&sample()).unwrap();
+    let pad_len = u32::from_be_bytes(frame[OFF_PAD_LEN..OFF_PAD_LEN + 4].try_into().unwrap());
+    frame[OFF_PAD_LEN..OFF_PAD_LEN + 4].copy_from_slice(&(pad_len - 1).to_be_bytes());
+    assert_eq!(
+        LatticeCodec::decode(&frame).err(),
+        Some(CodecError::PadLenMismatch)
+    );
+
+    // Or the frame is cut short
+    let frame = LatticeCodec::encode(&sample()).unwrap();
+    assert_eq!(
+        LatticeCodec::decode(&frame[..frame.len() - 1]).err(),
+        Some(CodecError::PadLenMismatch)
+    );
+}
+
+#[test]
+fn huge_length_fields_are_rejected() {
+    // Summed as usize these overflow a 32-bit target
+    let mut frame = LatticeCodec::encode(&sample()).unwrap();
+    frame[OFF_PAD_LEN..OFF_PAD_LEN + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+    frame[OFF_PAYLOAD_LEN..OFF_PAYLOAD_LEN + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+    assert_eq!(
+        LatticeCodec::decode(&frame).err(),
+        Some(CodecError::PadLenMismatch)
+    );
+}
+
+#[test]
+fn non_zero_padding_is_rejected() {
+    let mut frame = LatticeCodec::encode(&sample()).unwrap();
+    *frame.last_mut().unwrap() = 1;
+    assert_eq!(
+        LatticeCodec::decode(&frame).err(),
+        Some(CodecError::NonZeroPadding)
+    );
+}
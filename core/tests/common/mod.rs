@@ -0,0 +1,61 @@
+#![allow(dead_code)]
+
+use binling_core::asm;
+use binling_core::capsules::{Capsule, CapsuleHeader, ExecState, SquareSpace};
+use binling_core::codec::HEADER_LEN;
+use binling_core::events::VmEvent;
+use binling_core::integrity;
+use binling_core::lattice::Coord;
+use binling_core::policy::PolicyCore;
+use binling_core::vm::LatticeVM;
+
+// A sealed SS64 capsule running the BASM program `src`
+pub fn capsule(id: u32, at: Coord, src: &str) -> Capsule {
+    let payload = asm::assemble(src).expect("test program assembles");
+    let mut capsule = Capsule {
+        header: CapsuleHeader {
+            magic: *b"BLE1",
+            version_major: 0,
+            version_minor: 1,
+            flags: 0,
+            capsule_id: id,
+            ss_n: SquareSpace::SS64,
+            priority: 10,
+            coord_x: at.0,
+            coord_y: at.1,
+            coord_z: at.2,
+            header_len: HEADER_LEN as u16,
+            policy_len: 0,
+            payload_len: 0,
+            pad_len: 0,
+            dict_hash: [0; 32],
+            policy_core_hash: [0; 32],
+            capsule_hash: [0; 32],
+        },
+        policy_core: vec![],
+        payload,
+        state: ExecState::default(),
+    };
+    integrity::seal(&mut capsule).expect("test program fits SS64");
+    capsule
+}
+
+// capsule() carrying `core` as its Policy Core
+pub fn governed(id: u32, at: Coord, src: &str, core: &PolicyCore) -> Capsule {
+    let mut capsule = capsule(id, at, src);
+    capsule.policy_core = core.encode().expect("test policy encodes");
+    integrity::seal(&mut capsule).expect("test program fits SS64");
+    capsule
+}
+
+// Runs `cycles` cycles and returns the events they queued, without the
+// per-cycle summaries
+pub fn run(vm: &mut LatticeVM, cycles: usize) -> Vec<VmEvent> {
+    for _ in 0..cycles {
+        vm.next_cycle().expect("cycle runs");
+    }
+    vm.events
+        .drain(..)
+        .filter(|e| !matches!(e, VmEvent::CycleCompleted { .. }))
+        .collect()
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use binling_core::events::VmEvent;
+use binling_core::history::HistoryError;
+use binling_core::vm::LatticeVM;
+use common::capsule;
+
+// A counter with a snapshot every 4 cycles, 3 of them kept
+fn counter() -> LatticeVM {
+    let mut vm = LatticeVM::empty("history".into());
+    vm.activate(capsule(1500, (0, 0, 0), "loop: INC YIELD JMP loop"))
+        .unwrap();
+    vm.enable_history(4, 3);
+    vm
+}
+
+// Runs to cycle 10, admitting a second capsule before cycle 7, and returns
+// the digest after every cycle (index 0 is the start)
+fn run_to_ten(vm: &mut LatticeVM) -> Vec<[u8; 32]> {
+    let mut digests = vec![vm.state_digest()];
+    for cycle in 1..=10 {
+        if cycle == 7 {
+            vm.activate(capsule(1600, (3, 0, 0), "INC LOG HALT"))
+                .unwrap();
+        }
+        vm.next_cycle().unwrap();
+        digests.push(vm.state_digest());
+    }
+    digests
+}
+
+#[test]
+fn rewind_rebuilds_past_cycles_from_snapshots_and_journal() {
+    let mut vm = counter();
+    let digests = run_to_ten(&mut vm);
+    assert_eq!(vm.history().unwrap().range(), Some((0, 8)));
+
+    assert_eq!(
+        vm.rewind_to(11),
+        Err(HistoryError::InFuture {
+            cycle: 11,
+            current: 10
+        })
+    );
+    // Cycle 7 starts from the snapshot at 4 and re-admits capsule 1600
+    vm.rewind_to(7).unwrap();
+    assert_eq!(vm.cycle_count, 7);
+    assert_eq!(vm.state_digest(), digests[7]);
+    assert!(vm.capsule_at(3, 0, 0).is_some());
+    assert_eq!(vm.events, vec![VmEvent::Rewound { from: 10, to: 7 }]);
+    assert_eq!(vm.history().unwrap().range(), Some((0, 4)));
+
+    vm.step_back().unwrap();
+    assert_eq!(vm.state_digest(), digests[6]);
+    assert!(vm.capsule_at(3, 0, 0).is_none());
+}
+
+#[test]
+fn rewind_past_the_oldest_snapshot_is_refused() {
+    let mut vm = counter();
+    run_to_ten(&mut vm);
+    for _ in 0..4 {
+        vm.next_cycle().unwrap();
+    }
+    // The snapshot at 0 made way for the one at 12
+    assert_eq!(
+        vm.rewind_to(3),
+        Err(HistoryError::Unavailable {
+            cycle: 3,
+            oldest: 4
+        })
+    );
+    assert_eq!(vm.cycle_count, 14);
+
+    let mut plain = LatticeVM::empty("plain".into());
+    assert_eq!(plain.rewind_to(0), Err(HistoryError::Disabled));
+}
+
+#[test]
+fn seek_goes_both_ways() {
+    let mut vm = counter();
+    let digests = run_to_ten(&mut vm);
+
+    vm.seek(5).unwrap();
+    assert_eq!(vm.state_digest(), digests[5]);
+    // Forward from 5 without re-admitting 1600, which was discarded
+    vm.seek(10).unwrap();
+    assert_eq!(vm.cycle_count, 10);
+    assert!(vm.capsule_at(3, 0, 0).is_none());
+    assert_eq!(vm.next_queue[0].state.registers[0], 10);
+}
+
+#[test]
+fn fork_leaves_the_original_alone() {
+    let mut vm = counter();
+    let digests = run_to_ten(&mut vm);
+
+    let mut fork = vm.fork_at(7, "fork".into()).unwrap();
+    assert_eq!(fork.universe_id, "fork");
+    assert_eq!(fork.state_digest(), digests[7]);
+    assert_eq!(vm.cycle_count, 10);
+    assert_eq!(vm.state_digest(), digests[10]);
+
+    // The fork runs on by itself and matches the original's next cycle
+    fork.next_cycle().unwrap();
+    assert_eq!(fork.state_digest(), digests[8]);
+    assert!(fork.history().is_none());
+}
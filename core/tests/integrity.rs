@@ -0,0 +1,127 @@
+mod common;
+
+use binling_core::capsules::Capsule;
+use binling_core::codec::LatticeCodec;
+use binling_core::error::VmError;
+use binling_core::events::VmEvent;
+use binling_core::integrity::{self, IntegrityError};
+use binling_core::policy::PolicyCore;
+use binling_core::vm::LatticeVM;
+use common::{capsule, run};
+
+// A sealed capsule with a Policy Core that allows everything
+fn sealed() -> Capsule {
+    let mut capsule = capsule(1500, (0, 0, 0), "LDI R0 7 LOG HALT");
+    capsule.policy_core = PolicyCore::allow_all(b"integrity").encode().unwrap();
+    integrity::seal(&mut capsule).unwrap();
+    capsule
+}
+
+// What activate() says about `capsule`; nothing may be admitted on failure
+fn admit(capsule: Capsule) -> Result<(), VmError> {
+    let mut vm = LatticeVM::empty("integrity".into());
+    let result = vm.activate(capsule);
+    if result.is_err() {
+        assert!(vm.next_queue.is_empty());
+    }
+    result
+}
+
+fn rejected(reason: IntegrityError) -> Result<(), VmError> {
+    Err(VmError::Rejected {
+        capsule_id: 1500,
+        reason,
+    })
+}
+
+#[test]
+fn sealed_capsule_round_trips_and_runs() {
+    let capsule = sealed();
+    assert_eq!(integrity::verify(&capsule), Ok(()));
+
+    let frame = LatticeCodec::encode(&capsule).unwrap();
+    let decoded = LatticeCodec::decode(&frame).unwrap();
+    assert_eq!(decoded.header, capsule.header);
+    assert_eq!(integrity::verify(&decoded), Ok(()));
+
+    let mut vm = LatticeVM::empty("integrity".into());
+    vm.activate_encoded(&frame).unwrap();
+    assert_eq!(
+        run(&mut vm, 1),
+        vec![VmEvent::Logged {
+            capsule_id: 1500,
+            value: 7
+        }]
+    );
+}
+
+#[test]
+fn replicated_child_is_sealed() {
+    let mut vm = LatticeVM::empty("integrity".into());
+    let mut parent = capsule(1500, (0, 0, 0), "LDI R0 9 STORE 0 0 0 20 REPL 1 0 0 HALT");
+    parent.policy_core = PolicyCore::allow_all(b"integrity").encode().unwrap();
+    integrity::seal(&mut parent).unwrap();
+    vm.activate(parent).unwrap();
+    run(&mut vm, 1);
+
+    // A new id, a new cell and the byte the parent stored before cloning
+    let child = vm.capsule_at(1, 0, 0).unwrap();
+    assert_eq!(child.header.capsule_id, 1501);
+    assert_eq!(child.payload[20], 9);
+    assert_eq!(integrity::verify(child), Ok(()));
+    assert_eq!(admit(child.clone()), Ok(()));
+}
+
+#[test]
+fn tampered_payload_is_rejected() {
+    let mut capsule = sealed();
+    capsule.payload[2] = 9;
+    assert_eq!(
+        admit(capsule),
+        rejected(IntegrityError::CapsuleHashMismatch)
+    );
+}
+
+#[test]
+fn tampered_header_is_rejected() {
+    let mut capsule = sealed();
+    capsule.header.priority += 1;
+    assert_eq!(
+        admit(capsule),
+        rejected(IntegrityError::CapsuleHashMismatch)
+    );
+}
+
+#[test]
+fn tampered_policy_is_rejected() {
+    // Still a canonical core, just not the one that was sealed
+    let mut capsule = sealed();
+    capsule.policy_core = PolicyCore::allow_all(b"Integrity").encode().unwrap();
+    assert_eq!(
+        admit(capsule),
+        rejected(IntegrityError::PolicyCoreHashMismatch)
+    );
+
+    // Rehashing the core is not enough: the frame hash covers it too
+    let mut capsule = sealed();
+    capsule.policy_core = PolicyCore::allow_all(b"Integrity").encode().unwrap();
+    capsule.header.policy_core_hash = integrity::policy_core_hash(&capsule.policy_core);
+    assert_eq!(
+        admit(capsule),
+        rejected(IntegrityError::CapsuleHashMismatch)
+    );
+}
+
+#[test]
+fn stale_lengths_are_rejected() {
+    let mut capsule = sealed();
+    capsule.payload.push(0x01);
+    assert_eq!(admit(capsule), rejected(IntegrityError::StaleLengths));
+}
+
+#[test]
+fn wrong_dict_hash_is_rejected() {
+    let mut capsule = sealed();
+    capsule.header.dict_hash[0] ^= 1;
+    assert_eq!(admit(capsule), rejected(IntegrityError::DictHashMismatch));
+}
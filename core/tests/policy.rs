@@ -0,0 +1,177 @@
+mod common;
+
+use binling_core::events::VmEvent;
+use binling_core::policy::{
+    DataClass, HardConstraint, Operation, PolicyCore, PolicyError, ScopeRule, Target,
+};
+use binling_core::trap::Trap;
+use binling_core::vm::LatticeVM;
+use common::{governed, run};
+
+fn core(scope: Vec<ScopeRule>, constraints: Vec<HardConstraint>) -> PolicyCore {
+    PolicyCore {
+        intent: b"test".to_vec(),
+        scope,
+        constraints,
+    }
+}
+
+#[test]
+fn nothing_is_permitted_without_a_matching_rule() {
+    let here = (0, 0, 0);
+    let empty = core(vec![], vec![]);
+    assert!(!empty.permits(Operation::Compute, DataClass::Registers, here, here));
+
+    let reads = core(
+        vec![ScopeRule::allow(Operation::Read.bit(), Target::Any)],
+        vec![],
+    );
+    assert!(reads.permits(Operation::Read, DataClass::Code, here, (5, 5, 5)));
+    assert!(!reads.permits(Operation::Write, DataClass::Code, here, here));
+
+    // The first instruction of a capsule under an empty scope traps
+    let mut vm = LatticeVM::empty("policy".into());
+    vm.activate(governed(1500, here, "INC HALT", &empty))
+        .unwrap();
+    assert_eq!(
+        run(&mut vm, 1),
+        vec![VmEvent::Trapped {
+            capsule_id: 1500,
+            ip: 0,
+            trap: Trap::PolicyViolation {
+                operation: Operation::Compute,
+                target: here,
+            },
+            handled: false,
+        }]
+    );
+}
+
+#[test]
+fn the_last_matching_rule_wins() {
+    let (here, there) = ((0, 0, 0), (1, 0, 0));
+    let allow_all = ScopeRule::allow(Operation::ALL_BITS, Target::Any);
+    let deny_self_writes = ScopeRule::deny(Operation::Write.bit(), Target::SelfCell);
+
+    let narrowed = core(vec![allow_all, deny_self_writes], vec![]);
+    assert!(!narrowed.permits(Operation::Write, DataClass::Code, here, here));
+    assert!(narrowed.permits(Operation::Write, DataClass::Code, here, there));
+    assert!(narrowed.permits(Operation::Read, DataClass::Code, here, here));
+
+    // The broad rule last overrides the narrow one
+    let widened = core(vec![deny_self_writes, allow_all], vec![]);
+    assert!(widened.permits(Operation::Write, DataClass::Code, here, here));
+}
+
+#[test]
+fn rules_match_on_data_class_and_region() {
+    let here = (0, 0, 0);
+    let rule = ScopeRule {
+        data: DataClass::Output.bit(),
+        ..ScopeRule::allow(
+            Operation::ALL_BITS,
+            Target::Region {
+                min: (-1, -1, -1),
+                max: (1, 1, 1),
+            },
+        )
+    };
+    let core = core(vec![rule], vec![]);
+    assert!(core.permits(Operation::Emit, DataClass::Output, here, (1, -1, 0)));
+    assert!(!core.permits(Operation::Emit, DataClass::Output, here, (2, 0, 0)));
+    assert!(!core.permits(Operation::Emit, DataClass::Code, here, here));
+}
+
+#[test]
+fn instructions_are_checked_against_what_they_touch() {
+    // Everything but writes to the cell at +x
+    let policy = core(
+        vec![
+            ScopeRule::allow(Operation::ALL_BITS, Target::Any),
+            ScopeRule::deny(
+                Operation::Write.bit(),
+                Target::Region {
+                    min: (1, 0, 0),
+                    max: (1, 0, 0),
+                },
+            ),
+        ],
+        vec![],
+    );
+    let mut vm = LatticeVM::empty("policy".into());
+    vm.activate(governed(
+        1500,
+        (0, 0, 0),
+        "LDI R0 1 SEND 1 0 0 STORE 0 1 0 9 STORE 1 0 0 9 HALT",
+        &policy,
+    ))
+    .unwrap();
+
+    // SEND is a Message and the STORE to +y is allowed; the STORE to +x
+    // traps at its offset
+    let events = run(&mut vm, 1);
+    assert_eq!(
+        events[0],
+        VmEvent::Trapped {
+            capsule_id: 1500,
+            ip: 12,
+            trap: Trap::PolicyViolation {
+                operation: Operation::Write,
+                target: (1, 0, 0),
+            },
+            handled: false,
+        }
+    );
+    assert!(matches!(
+        events[1..],
+        [
+            VmEvent::Wrote {
+                target: (0, 1, 0),
+                ..
+            },
+            VmEvent::Sent {
+                target: (1, 0, 0),
+                ..
+            },
+        ]
+    ));
+}
+
+#[test]
+fn a_capsule_halts_with_an_event_after_its_deadline() {
+    let policy = core(
+        vec![ScopeRule::allow(Operation::ALL_BITS, Target::Any)],
+        vec![HardConstraint::Deadline(2)],
+    );
+    let mut vm = LatticeVM::empty("policy".into());
+    vm.activate(governed(1500, (0, 0, 0), "INC LOG YIELD JMP 0", &policy))
+        .unwrap();
+
+    let logged = |value| VmEvent::Logged {
+        capsule_id: 1500,
+        value,
+    };
+    assert_eq!(run(&mut vm, 2), vec![logged(1), logged(2)]);
+    assert_eq!(
+        run(&mut vm, 1),
+        vec![VmEvent::Expired {
+            capsule_id: 1500,
+            deadline: 2,
+        }]
+    );
+    assert!(run(&mut vm, 2).is_empty());
+}
+
+#[test]
+fn overlong_fields_are_not_encoded() {
+    let fits = PolicyCore::allow_all(&vec![0; u16::MAX as usize]);
+    assert!(fits.encode().is_ok());
+    let intent = PolicyCore::allow_all(&vec![0; u16::MAX as usize + 1]);
+    assert_eq!(intent.encode(), Err(PolicyError::TooLong));
+
+    let rules = core(
+        vec![ScopeRule::deny(Operation::Void.bit(), Target::Any); u16::MAX as usize + 1],
+        vec![],
+    );
+    assert_eq!(rules.encode(), Err(PolicyError::TooLong));
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use binling_core::config::Occupancy;
+use binling_core::error::VmError;
+use binling_core::vm::LatticeVM;
+use common::{capsule, run};
+
+#[test]
+fn replay_matches_the_recorded_digests() {
+    let mut vm = LatticeVM::empty("trace".into());
+    vm.activate(capsule(1500, (0, 0, 0), "INC LOG REPL 1 0 0 YIELD JMP 0"))
+        .unwrap();
+    vm.start_trace();
+    run(&mut vm, 2);
+    // An injection mid-recording is replayed at the same cycle
+    vm.activate(capsule(1600, (5, 0, 0), "INC LOG HALT"))
+        .unwrap();
+    run(&mut vm, 3);
+    let trace = vm.stop_trace().unwrap();
+    assert_eq!(trace.digests.len(), 5);
+    assert_eq!(trace.injections.len(), 1);
+
+    let replayed = trace.replay().unwrap();
+    assert_eq!(replayed.state_digest(), vm.state_digest());
+
+    let mut tampered = trace.clone();
+    tampered.digests[3].1[0] ^= 1;
+    assert_eq!(tampered.replay().err().map(|d| d.cycle), Some(4));
+}
+
+#[test]
+fn digest_covers_the_config_and_the_halt_reason() {
+    let vm = LatticeVM::empty("digest".into());
+
+    let mut stacked = vm.clone();
+    stacked.config.occupancy = Occupancy::Stacked;
+    assert_ne!(stacked.state_digest(), vm.state_digest());
+    let mut gassed = vm.clone();
+    gassed.config.cycle_gas = Some(10);
+    assert_ne!(gassed.state_digest(), vm.state_digest());
+
+    let conflict = |second_writer| VmError::WriteConflict {
+        target: (0, 0, 0),
+        idx: 3,
+        first_writer: 1500,
+        second_writer,
+    };
+    let mut halted = vm.clone();
+    halted.halted = Some(conflict(1501));
+    let mut halted_otherwise = vm.clone();
+    halted_otherwise.halted = Some(conflict(1502));
+    assert_ne!(halted.state_digest(), vm.state_digest());
+    assert_ne!(halted.state_digest(), halted_otherwise.state_digest());
+}
+
+#[cfg(feature = "cli-mode")]
+#[test]
+fn loaded_trace_knows_which_cells_are_taken() {
+    use binling_core::trace::Trace;
+
+    let mut vm = LatticeVM::empty("trace".into());
+    vm.activate(capsule(1500, (2, 0, 0), "YIELD")).unwrap();
+    vm.start_trace();
+    run(&mut vm, 1);
+    let path = std::env::temp_dir().join(format!("binling_trace_{}.bin", std::process::id()));
+    let path = path.to_str().unwrap();
+    vm.stop_trace().unwrap().save(path).unwrap();
+    let loaded = Trace::load(path);
+    std::fs::remove_file(path).unwrap();
+
+    let loaded = loaded.unwrap();
+    assert_eq!(
+        loaded.base.capsule_at(2, 0, 0).map(|c| c.header.capsule_id),
+        Some(1500)
+    );
+}
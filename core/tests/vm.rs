@@ -0,0 +1,878 @@
+mod common;
+
+use binling_core::asm;
+use binling_core::capsules::{CapsuleRole, CapsuleStatus, SquareSpace, FLAG_EQUAL, FLAG_LESS};
+use binling_core::codec::{LatticeCodec, HEADER_LEN};
+use binling_core::config::{MovePolicy, Occupancy};
+use binling_core::error::VmError;
+use binling_core::events::VmEvent;
+use binling_core::integrity;
+use binling_core::policy::{HardConstraint, Operation, PolicyCore, ScopeRule, Target};
+use binling_core::scenario::{Scenario, ScenarioError};
+use binling_core::trap::Trap;
+use binling_core::vm::LatticeVM;
+use common::{capsule, governed, run};
+
+#[test]
+fn live_ids_are_refused_at_admission() {
+    let mut vm = LatticeVM::empty("dup".into());
+    vm.activate(capsule(1500, (0, 0, 0), "HALT")).unwrap();
+    assert_eq!(
+        vm.activate(capsule(1500, (1, 0, 0), "HALT")),
+        Err(VmError::DuplicateId { capsule_id: 1500 })
+    );
+    assert_eq!(vm.next_queue.len(), 1);
+    assert!(vm.capsule_at(1, 0, 0).is_none());
+}
+
+#[test]
+fn duplicate_ids_are_removed_with_an_event() {
+    let mut vm = LatticeVM::empty("dup".into());
+    vm.activate(capsule(1500, (0, 0, 0), "HALT")).unwrap();
+    // Admission refuses a live id, so only a queue edited by hand can hold
+    // one twice
+    vm.next_queue.push(capsule(1500, (1, 0, 0), "HALT"));
+    vm.rebuild_index();
+
+    let events = run(&mut vm, 1);
+    assert_eq!(
+        events,
+        vec![VmEvent::Duplicate {
+            capsule_id: 1500,
+            at: (1, 0, 0)
+        }]
+    );
+    assert_eq!(vm.next_queue.len(), 1);
+    assert!(vm.capsule_at(0, 0, 0).is_some());
+}
+
+#[test]
+fn lookup_after_the_queue_shrinks_finds_nothing() {
+    let mut vm = LatticeVM::empty("lookup".into());
+    vm.activate(capsule(1500, (0, 0, 0), "HALT")).unwrap();
+    vm.activate(capsule(1501, (1, 0, 0), "HALT")).unwrap();
+
+    // The index still names the removed slot until it is rebuilt
+    vm.next_queue.pop();
+    assert!(vm.capsule_at(1, 0, 0).is_none());
+    vm.rebuild_index();
+    assert!(vm.capsule_at(1, 0, 0).is_none());
+    assert_eq!(vm.capsule_at(0, 0, 0).unwrap().header.capsule_id, 1500);
+}
+
+#[test]
+fn store_to_an_empty_cell_is_reported_as_dropped() {
+    let mut vm = LatticeVM::empty("store".into());
+    vm.activate(capsule(1500, (0, 0, 0), "LDI R0 9 STORE 1 0 0 5 HALT"))
+        .unwrap();
+
+    let events = run(&mut vm, 1);
+    assert_eq!(
+        events,
+        vec![VmEvent::Wrote {
+            writer: 1500,
+            target: (1, 0, 0),
+            idx: 5,
+            value: 9,
+            applied: false,
+        }]
+    );
+}
+
+#[test]
+fn offsets_wrap_at_the_lattice_edge() {
+    let mut vm = LatticeVM::empty("edge".into());
+    // GET's register byte used to be read as an x offset and overflowed
+    vm.activate(capsule(
+        1500,
+        (i16::MAX, 0, 0),
+        "LDI R1 1 GET R1 SENSE 1 0 0 PUT R2 LDI R0 7 SEND 1 0 0 STORE 1 0 0 20 HALT",
+    ))
+    .unwrap();
+    vm.activate(capsule(1501, (i16::MIN, 0, 0), "RECV LOG HALT"))
+        .unwrap();
+    vm.activate(capsule(1502, (0, i16::MIN, 0), "MOVE 0 -1 0 HALT"))
+        .unwrap();
+
+    let events = run(&mut vm, 2);
+    assert_eq!(vm.capsule_at(i16::MAX, 0, 0).unwrap().state.registers[2], 1);
+    assert!(events.contains(&VmEvent::Wrote {
+        writer: 1500,
+        target: (i16::MIN, 0, 0),
+        idx: 20,
+        value: 7,
+        applied: true,
+    }));
+    assert!(events.contains(&VmEvent::Logged {
+        capsule_id: 1501,
+        value: 7,
+    }));
+    assert_eq!(
+        vm.capsule_at(0, i16::MAX, 0).unwrap().header.capsule_id,
+        1502
+    );
+}
+
+#[test]
+fn runtime_ids_are_allocated_above_injected_ids() {
+    let mut vm = LatticeVM::empty("ids".into());
+    vm.activate(capsule(1000, (0, 0, 0), "HALT")).unwrap();
+    vm.activate(capsule(1001, (5, 0, 0), "REPL 1 0 0 HALT"))
+        .unwrap();
+
+    let events = run(&mut vm, 1);
+    assert_eq!(
+        events,
+        vec![VmEvent::Replicated {
+            parent: 1001,
+            child: 1002,
+            at: (6, 0, 0),
+        }]
+    );
+    assert_eq!(vm.next_queue.len(), 3);
+    assert_eq!(vm.allocate_id(), Some(1003));
+}
+
+#[test]
+fn ids_run_out_instead_of_wrapping() {
+    let mut vm = LatticeVM::empty("ids".into());
+    assert_eq!(
+        vm.activate(capsule(u32::MAX, (0, 0, 0), "HALT")),
+        Err(VmError::IdOutOfRange {
+            capsule_id: u32::MAX
+        })
+    );
+    // The last usable id leaves none for a child
+    vm.activate(capsule(u32::MAX - 1, (0, 0, 0), "REPL 1 0 0 HALT"))
+        .unwrap();
+    assert_eq!(
+        run(&mut vm, 1),
+        vec![VmEvent::Trapped {
+            capsule_id: u32::MAX - 1,
+            ip: 0,
+            trap: Trap::IdsExhausted,
+            handled: false,
+        }]
+    );
+    assert_eq!(vm.next_queue.len(), 1);
+    assert_eq!(vm.allocate_id(), None);
+}
+
+#[test]
+fn remote_store_past_the_frame_traps_the_writer() {
+    let mut vm = LatticeVM::empty("capacity".into());
+    // An SS8 frame with a large Policy Core leaves room for 124 bytes
+    let mut target = capsule(1501, (1, 0, 0), "HALT");
+    target.header.ss_n = SquareSpace::SS8;
+    target.policy_core = PolicyCore::allow_all(&[b'x'; 250]).encode().unwrap();
+    integrity::seal(&mut target).unwrap();
+    let room = 512 - HEADER_LEN - target.policy_core.len();
+    assert!(room > 100 && room <= 200);
+    vm.activate(target).unwrap();
+    vm.activate(capsule(
+        1500,
+        (0, 0, 0),
+        "LDI R0 5 STORE 1 0 0 100 STORE 1 0 0 200 HALT",
+    ))
+    .unwrap();
+
+    // The HALT after the second STORE has run by the boundary; the writer
+    // is rewound to that STORE and traps there
+    let events = run(&mut vm, 1);
+    assert_eq!(
+        events,
+        vec![
+            VmEvent::Wrote {
+                writer: 1500,
+                target: (1, 0, 0),
+                idx: 100,
+                value: 5,
+                applied: true,
+            },
+            VmEvent::Wrote {
+                writer: 1500,
+                target: (1, 0, 0),
+                idx: 200,
+                value: 5,
+                applied: false,
+            },
+            VmEvent::Trapped {
+                capsule_id: 1500,
+                ip: 8,
+                trap: Trap::CapacityExceeded,
+                handled: false,
+            },
+        ]
+    );
+    let target = vm.capsule_at(1, 0, 0).unwrap();
+    assert_eq!(target.payload.len(), 101);
+    assert!(LatticeCodec::encode(target).is_ok());
+    let writer = vm.capsule_at(0, 0, 0).unwrap();
+    assert_eq!(writer.state.status, CapsuleStatus::Faulted);
+    assert_eq!(writer.state.ip, 8);
+}
+
+#[test]
+fn trap_at_a_remote_store_undoes_what_ran_after_it() {
+    let mut vm = LatticeVM::empty("capacity".into());
+    // No room for byte 250, as above
+    let mut target = capsule(1501, (1, 0, 0), "HALT");
+    target.header.ss_n = SquareSpace::SS8;
+    target.policy_core = PolicyCore::allow_all(&[b'x'; 250]).encode().unwrap();
+    integrity::seal(&mut target).unwrap();
+    vm.activate(target).unwrap();
+    vm.activate(capsule(
+        1500,
+        (0, 0, 0),
+        "LDI R0 5 STORE 1 0 0 20 STORE 1 0 0 250 INC STORE 0 0 0 40 SEND 1 0 0 HALT",
+    ))
+    .unwrap();
+
+    let events = run(&mut vm, 1);
+    // The first STORE lands; the INC, the own-cell STORE and the SEND after
+    // the one that traps never happened
+    assert!(events.contains(&VmEvent::Wrote {
+        writer: 1500,
+        target: (1, 0, 0),
+        idx: 20,
+        value: 5,
+        applied: true,
+    }));
+    assert!(!events.iter().any(|e| matches!(e, VmEvent::Sent { .. })));
+    let writer = vm.capsule_at(0, 0, 0).unwrap();
+    assert_eq!(writer.state.status, CapsuleStatus::Faulted);
+    assert_eq!(writer.state.ip, 8);
+    assert_eq!(writer.state.registers[0], 5);
+    assert_eq!(writer.state.fault, Some(Trap::CapacityExceeded));
+    assert_eq!(
+        writer.payload,
+        asm::assemble("LDI R0 5 STORE 1 0 0 20 STORE 1 0 0 250 INC STORE 0 0 0 40 SEND 1 0 0 HALT")
+            .unwrap()
+    );
+    assert!(vm.capsule_at(1, 0, 0).unwrap().state.mailbox.is_empty());
+}
+
+#[test]
+fn oracle_message_written_in_one_activation_is_one_output() {
+    let mut vm = LatticeVM::new("oracle".into());
+    let cell = vm.free_cell_near((0, 0, 0));
+    assert_eq!(cell, (0, 0, 0));
+    let id = vm.allocate_id().unwrap();
+    vm.activate(capsule(
+        id,
+        cell,
+        "LDI R0 72 STORE -10 0 0 0 LDI R0 73 STORE -10 0 0 1 HALT",
+    ))
+    .unwrap();
+
+    // "HI", answered reversed as one message
+    run(&mut vm, 2);
+    assert_eq!(vm.output_buffer, vec!["IH".to_string()]);
+}
+
+#[test]
+fn swap_never_displaces_a_capsule_that_moved_this_cycle() {
+    let mut vm = LatticeVM::empty("swap".into());
+    vm.config.move_policy = MovePolicy::Swap;
+    vm.activate(capsule(1500, (0, 0, 0), "MOVE 1 0 0")).unwrap();
+    vm.activate(capsule(1501, (2, 0, 0), "MOVE -1 0 0"))
+        .unwrap();
+    // A capsule that stayed put is still swapped with
+    vm.activate(capsule(1502, (5, 0, 0), "MOVE 1 0 0")).unwrap();
+    vm.activate(capsule(1503, (6, 0, 0), "HALT")).unwrap();
+
+    let events = run(&mut vm, 1);
+    assert_eq!(
+        events,
+        vec![
+            VmEvent::Moved {
+                capsule_id: 1500,
+                from: (0, 0, 0),
+                to: (1, 0, 0)
+            },
+            VmEvent::MoveBlocked {
+                capsule_id: 1501,
+                at: (2, 0, 0),
+                target: (1, 0, 0)
+            },
+            VmEvent::Moved {
+                capsule_id: 1502,
+                from: (5, 0, 0),
+                to: (6, 0, 0)
+            },
+            VmEvent::Moved {
+                capsule_id: 1503,
+                from: (6, 0, 0),
+                to: (5, 0, 0)
+            },
+        ]
+    );
+    assert_eq!(vm.capsule_at(1, 0, 0).unwrap().header.capsule_id, 1500);
+    assert_eq!(vm.capsule_at(2, 0, 0).unwrap().header.capsule_id, 1501);
+}
+
+#[test]
+fn capsules_moving_into_each_other_swap_once() {
+    let mut vm = LatticeVM::empty("swap".into());
+    vm.config.move_policy = MovePolicy::Swap;
+    vm.activate(capsule(1500, (0, 0, 0), "MOVE 1 0 0")).unwrap();
+    vm.activate(capsule(1501, (1, 0, 0), "MOVE 1 0 0")).unwrap();
+
+    // 1501 is displaced before its own MOVE is carried out, so that MOVE
+    // is dropped rather than taken from the cell it was swapped into
+    assert_eq!(
+        run(&mut vm, 1),
+        vec![
+            VmEvent::Moved {
+                capsule_id: 1500,
+                from: (0, 0, 0),
+                to: (1, 0, 0)
+            },
+            VmEvent::Moved {
+                capsule_id: 1501,
+                from: (1, 0, 0),
+                to: (0, 0, 0)
+            },
+        ]
+    );
+    assert_eq!(vm.capsule_at(0, 0, 0).unwrap().header.capsule_id, 1501);
+    assert_eq!(vm.capsule_at(1, 0, 0).unwrap().header.capsule_id, 1500);
+    assert!(vm.capsule_at(2, 0, 0).is_none());
+}
+
+#[test]
+fn repeated_injections_find_a_free_cell() {
+    let mut vm = LatticeVM::empty("inject".into());
+    vm.activate(capsule(1500, (0, 0, 0), "YIELD")).unwrap();
+    // What the CLI oracle does for every program dropped into oracle_in.txt
+    let mut cells = vec![(0, 0, 0)];
+    for _ in 0..30 {
+        let cell = vm.free_cell_near((0, 0, 0));
+        let id = vm.allocate_id().unwrap();
+        vm.activate(capsule(id, cell, "YIELD")).unwrap();
+        cells.push(cell);
+    }
+    cells.sort();
+    cells.dedup();
+    assert_eq!(cells.len(), 31);
+    // 27 cells fill the first shell, so the last injections went one further
+    assert!(cells.contains(&(-2, -2, -2)));
+    assert!(run(&mut vm, 1).is_empty());
+
+    vm.config.occupancy = Occupancy::Stacked;
+    assert_eq!(vm.free_cell_near((0, 0, 0)), (0, 0, 0));
+}
+
+#[test]
+fn spawn_runs_a_slice_of_the_parent() {
+    let mut vm = LatticeVM::empty("spawn".into());
+    let policy = PolicyCore::allow_all(b"builder");
+    // Bytes 7..10 are INC LOG HALT
+    vm.activate_with_role(
+        governed(7, (0, 0, 0), "SPAWN 1 0 0 7 3 HALT INC LOG HALT", &policy),
+        CapsuleRole::Kernel,
+    )
+    .unwrap();
+
+    assert_eq!(
+        run(&mut vm, 1),
+        vec![VmEvent::Spawned {
+            parent: 7,
+            child: 1000,
+            at: (1, 0, 0)
+        }]
+    );
+    let child = vm.capsule_at(1, 0, 0).unwrap();
+    assert_eq!(child.payload, asm::assemble("INC LOG HALT").unwrap());
+    // A program of its own: no kernel rights, but the parent's Policy Core
+    assert_eq!(child.state.role, CapsuleRole::UserProgram);
+    assert_eq!(child.state.status, CapsuleStatus::Running);
+    assert_eq!(child.policy_core, policy.encode().unwrap());
+    assert_eq!(integrity::verify(child), Ok(()));
+
+    assert_eq!(
+        run(&mut vm, 1),
+        vec![VmEvent::Logged {
+            capsule_id: 1000,
+            value: 1
+        }]
+    );
+}
+
+#[test]
+fn spawn_of_a_blank_brick_is_dormant_structure() {
+    let mut vm = LatticeVM::empty("spawn".into());
+    let policy = PolicyCore::allow_all(b"builder");
+    vm.activate(governed(1500, (0, 0, 0), "SPAWN 0 1 0 0 0 HALT", &policy))
+        .unwrap();
+    run(&mut vm, 1);
+    let brick = vm.capsule_at(0, 1, 0).unwrap();
+    assert_eq!(brick.state.role, CapsuleRole::Structure);
+    assert_eq!(brick.state.status, CapsuleStatus::Dormant);
+    assert_eq!(brick.payload, vec![0; 64]);
+    assert_eq!(brick.policy_core, policy.encode().unwrap());
+    assert_eq!(integrity::verify(brick), Ok(()));
+}
+
+#[test]
+fn an_activated_brick_keeps_the_parent_policy() {
+    let mut vm = LatticeVM::empty("spawn".into());
+    let mut policy = PolicyCore::allow_all(b"no output");
+    policy
+        .scope
+        .push(ScopeRule::deny(Operation::Emit.bit(), Target::Any));
+    // Writes a LOG into a fresh brick and wakes it
+    vm.activate(governed(
+        1500,
+        (0, 0, 0),
+        "SPAWN 0 1 0 0 0 LDI R0 7 STORE 0 1 0 0 ACTIVATE_NEIGHBOR 0 1 0 HALT",
+        &policy,
+    ))
+    .unwrap();
+
+    let events = run(&mut vm, 4);
+    assert!(!events.iter().any(|e| matches!(e, VmEvent::Logged { .. })));
+    assert!(events.contains(&VmEvent::Trapped {
+        capsule_id: 1501,
+        ip: 0,
+        trap: Trap::PolicyViolation {
+            operation: Operation::Emit,
+            target: (0, 1, 0),
+        },
+        handled: false,
+    }));
+}
+
+#[test]
+fn spawn_past_the_payload_traps() {
+    let mut vm = LatticeVM::empty("spawn".into());
+    vm.activate(capsule(
+        1500,
+        (0, 0, 0),
+        "SPAWN 1 0 0 7 4 HALT INC LOG HALT",
+    ))
+    .unwrap();
+    assert_eq!(
+        run(&mut vm, 1),
+        vec![VmEvent::Trapped {
+            capsule_id: 1500,
+            ip: 0,
+            trap: Trap::SliceOutOfRange { start: 7, len: 4 },
+            handled: false,
+        }]
+    );
+    assert!(vm.capsule_at(1, 0, 0).is_none());
+    assert_eq!(vm.next_queue[0].state.status, CapsuleStatus::Faulted);
+    assert_eq!(vm.next_queue[0].state.replications, 0);
+}
+
+#[test]
+fn activate_wakes_a_distant_dormant_capsule_once() {
+    let mut vm = LatticeVM::empty("activate".into());
+    // Out of reach of an i8 coordinate
+    let target = capsule(1501, (300, -200, 0), "INC LOG DORMANT");
+    vm.activate_with_role(target, CapsuleRole::Structure)
+        .unwrap();
+    for (id, at) in [(1500, (0, 0, 0)), (1502, (1, 0, 0))] {
+        vm.activate(capsule(id, at, "ACTIVATE 300 -200 0 HALT"))
+            .unwrap();
+    }
+
+    // Both wake it in cycle 1; it fires once in cycle 2
+    assert_eq!(run(&mut vm, 1), vec![]);
+    assert_eq!(
+        run(&mut vm, 2),
+        vec![VmEvent::Logged {
+            capsule_id: 1501,
+            value: 1
+        }]
+    );
+    let woken = vm.capsule_at(300, -200, 0).unwrap();
+    assert_eq!(woken.state.registers[0], 1);
+    assert_eq!(woken.state.status, CapsuleStatus::Dormant);
+}
+
+#[test]
+fn genesis_reports_a_refused_capsule() {
+    let mut vm = LatticeVM::empty("genesis".into());
+    vm.genesis().unwrap();
+    assert_eq!(vm.next_queue.len(), 50);
+    // Cells are exclusive here, and the kernel's is already taken
+    assert!(matches!(
+        vm.genesis(),
+        Err(ScenarioError::Rejected(VmError::Occupied {
+            capsule_id: 1,
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn scenario_is_placed_whole_or_not_at_all() {
+    let mut scenario = Scenario::from_json(
+        r#"{"name": "clash", "capsules": [
+            {"id": 1500, "at": [4, 0, 0], "code": "HALT"},
+            {"id": 1501, "at": [5, 0, 0], "code": "HALT"},
+            {"id": 1502, "at": [5, 0, 0], "code": "HALT"}
+        ]}"#,
+    )
+    .unwrap();
+    let mut vm = LatticeVM::empty("scenario".into());
+    assert_eq!(
+        vm.load_scenario(&scenario),
+        Err(ScenarioError::Rejected(VmError::Occupied {
+            capsule_id: 1502,
+            at: (5, 0, 0),
+        }))
+    );
+    assert!(vm.next_queue.is_empty());
+    assert!(vm.capsule_at(4, 0, 0).is_none());
+
+    // Nothing was left behind to clash with a corrected scenario
+    scenario.capsules.pop();
+    vm.load_scenario(&scenario).unwrap();
+    assert_eq!(vm.next_queue.len(), 2);
+}
+
+#[test]
+fn own_cell_store_stays_with_a_writer_that_moves() {
+    let mut vm = LatticeVM::empty("store".into());
+    vm.activate(capsule(
+        1500,
+        (0, 0, 0),
+        "LDI R0 99 STORE 0 0 0 30 MOVE 0 1 0",
+    ))
+    .unwrap();
+    vm.activate(capsule(1501, (1, 0, 0), "MOVE -1 0 0"))
+        .unwrap();
+
+    let events = run(&mut vm, 1);
+    assert!(events.iter().all(|e| matches!(e, VmEvent::Moved { .. })));
+    let writer = vm.capsule_at(0, 1, 0).unwrap();
+    assert_eq!(writer.header.capsule_id, 1500);
+    assert_eq!(writer.payload[30], 99);
+    // The capsule that took the writer's old cell is untouched
+    let newcomer = vm.capsule_at(0, 0, 0).unwrap();
+    assert_eq!(newcomer.header.capsule_id, 1501);
+    assert_eq!(newcomer.payload, asm::assemble("MOVE -1 0 0").unwrap());
+}
+
+#[test]
+fn own_cell_store_conflicts_with_a_remote_store() {
+    let mut vm = LatticeVM::empty("store".into());
+    vm.activate(capsule(1500, (0, 0, 0), "LDI R0 1 STORE 0 0 0 30 HALT"))
+        .unwrap();
+    vm.activate(capsule(1501, (1, 0, 0), "LDI R0 2 STORE -1 0 0 30 HALT"))
+        .unwrap();
+
+    let conflict = VmError::WriteConflict {
+        target: (0, 0, 0),
+        idx: 30,
+        first_writer: 1500,
+        second_writer: 1501,
+    };
+    assert_eq!(vm.next_cycle(), Err(conflict.clone()));
+    assert_eq!(vm.halted, Some(conflict));
+}
+
+#[test]
+fn blocked_repl_traps_before_anything_after_it_runs() {
+    let mut vm = LatticeVM::empty("repl".into());
+    vm.activate(capsule(1500, (0, 0, 0), "REPL 1 0 0 INC LOG HALT"))
+        .unwrap();
+    vm.activate(capsule(1501, (1, 0, 0), "YIELD")).unwrap();
+
+    // No Replicated for the child that was never placed, and no LOG from
+    // the instructions behind the REPL
+    assert_eq!(
+        run(&mut vm, 1),
+        vec![
+            VmEvent::BirthBlocked {
+                parent: 1500,
+                child: 1502,
+                at: (1, 0, 0),
+            },
+            VmEvent::Trapped {
+                capsule_id: 1500,
+                ip: 0,
+                trap: Trap::CellOccupied { target: (1, 0, 0) },
+                handled: false,
+            },
+        ]
+    );
+    let parent = vm.capsule_at(0, 0, 0).unwrap();
+    assert_eq!(parent.state.registers[0], 0);
+    assert_eq!(parent.state.status, CapsuleStatus::Faulted);
+}
+
+#[test]
+fn star_fortress_keeps_the_origin_free_for_programs() {
+    let mut vm = LatticeVM::new("fortress".into());
+    assert_eq!(vm.config.occupancy, Occupancy::Exclusive);
+    assert_eq!(vm.free_cell_near((0, 0, 0)), (0, 0, 0));
+    vm.activate(capsule(
+        1500,
+        (0, 0, 0),
+        "SPAWN 1 1 0 0 0 SPAWN -1 -1 0 0 0 HALT",
+    ))
+    .unwrap();
+
+    // The diagonals between the arms are free as well
+    let events = run(&mut vm, 2);
+    assert!(!events
+        .iter()
+        .any(|e| matches!(e, VmEvent::Trapped { .. } | VmEvent::BirthBlocked { .. })));
+    assert!(vm.capsule_at(1, 1, 0).is_some());
+    assert!(vm.capsule_at(-1, -1, 0).is_some());
+}
+
+#[test]
+fn each_capsule_keeps_its_own_registers() {
+    let mut vm = LatticeVM::empty("registers".into());
+    vm.activate(capsule(1500, (0, 0, 0), "LDI R2 40 INC INC YIELD INC HALT"))
+        .unwrap();
+    vm.activate(capsule(1501, (5, 0, 0), "LDI R3 -7 DEC REPL 1 0 0"))
+        .unwrap();
+
+    run(&mut vm, 1);
+    let registers = |vm: &LatticeVM, x| vm.capsule_at(x, 0, 0).unwrap().state.registers;
+    assert_eq!(registers(&vm, 0), [2, 0, 40, 0]);
+    assert_eq!(registers(&vm, 5), [-1, 0, 0, -7]);
+    // A REPL child starts with a copy of its parent's registers
+    assert_eq!(registers(&vm, 6), [-1, 0, 0, -7]);
+
+    run(&mut vm, 1);
+    assert_eq!(registers(&vm, 0), [3, 0, 40, 0]);
+}
+
+#[test]
+fn capsule_at_follows_moves_births_and_voids() {
+    let mut vm = LatticeVM::empty("index".into());
+    vm.activate(capsule(1500, (0, 0, 0), "MOVE 2 0 0 HALT"))
+        .unwrap();
+    vm.activate(capsule(1501, (0, 5, 0), "SPAWN 0 1 0 0 0 HALT"))
+        .unwrap();
+    vm.activate(capsule(1502, (0, 0, 5), "VOID")).unwrap();
+    let id_at = |vm: &LatticeVM, x, y, z| vm.capsule_at(x, y, z).map(|c| c.header.capsule_id);
+    assert_eq!(id_at(&vm, 0, 0, 5), Some(1502));
+
+    run(&mut vm, 1);
+    assert_eq!(id_at(&vm, 0, 0, 0), None);
+    assert_eq!(id_at(&vm, 2, 0, 0), Some(1500));
+    assert_eq!(id_at(&vm, 0, 5, 0), Some(1501));
+    assert_eq!(id_at(&vm, 0, 6, 0), Some(1503));
+    assert_eq!(id_at(&vm, 0, 0, 5), None);
+}
+
+#[test]
+fn a_cycle_reports_why_the_population_changed() {
+    let mut vm = LatticeVM::empty("events".into());
+    vm.activate(capsule(1500, (0, 0, 0), "LDI R0 3 LOG REPL 1 0 0"))
+        .unwrap();
+    vm.activate(capsule(1501, (5, 0, 0), "VOID")).unwrap();
+
+    vm.next_cycle().unwrap();
+    assert_eq!(
+        vm.events,
+        vec![
+            VmEvent::Logged {
+                capsule_id: 1500,
+                value: 3
+            },
+            VmEvent::Voided {
+                capsule_id: 1501,
+                at: (5, 0, 0)
+            },
+            VmEvent::Replicated {
+                parent: 1500,
+                child: 1502,
+                at: (1, 0, 0)
+            },
+            VmEvent::CycleCompleted {
+                cycle: 1,
+                population: 2
+            },
+        ]
+    );
+    assert!(vm.output_buffer.is_empty());
+}
+
+#[test]
+fn instruction_budget_and_cycle_gas_bound_each_cycle() {
+    let mut vm = LatticeVM::empty("budget".into());
+    vm.config.instruction_budget = 3;
+    vm.activate(capsule(1500, (0, 0, 0), "INC INC INC INC INC HALT"))
+        .unwrap();
+    run(&mut vm, 1);
+    assert_eq!(vm.next_queue[0].state.registers[0], 3);
+    assert_eq!(vm.next_queue[0].state.ip, 3);
+    run(&mut vm, 1);
+    assert_eq!(vm.next_queue[0].state.registers[0], 5);
+
+    // 4 instructions for the whole lattice: the first capsule in the
+    // scheduling order takes them all, the next one waits
+    let mut vm = LatticeVM::empty("gas".into());
+    vm.config.cycle_gas = Some(4);
+    vm.activate(capsule(1500, (0, 0, 0), "INC INC INC INC INC INC YIELD"))
+        .unwrap();
+    vm.activate(capsule(1501, (1, 0, 0), "INC INC INC YIELD"))
+        .unwrap();
+    let r0 = |vm: &LatticeVM, x| vm.capsule_at(x, 0, 0).unwrap().state.registers[0];
+    run(&mut vm, 1);
+    assert_eq!((r0(&vm, 0), r0(&vm, 1)), (4, 0));
+    run(&mut vm, 1);
+    assert_eq!((r0(&vm, 0), r0(&vm, 1)), (6, 1));
+}
+
+#[test]
+fn capsules_left_without_gas_do_not_change() {
+    let mut vm = LatticeVM::empty("gas".into());
+    vm.config.cycle_gas = Some(2);
+    vm.activate(capsule(1500, (0, 0, 0), "l: INC JMP l"))
+        .unwrap();
+    // Past its deadline, and waiting: both would change if it fired
+    let mut policy = PolicyCore::allow_all(b"late");
+    policy.constraints.push(HardConstraint::Deadline(0));
+    let mut late = governed(1501, (1, 0, 0), "HALT", &policy);
+    late.state.wait = 5;
+    vm.activate(late).unwrap();
+
+    assert_eq!(run(&mut vm, 2), vec![]);
+    let late = vm.capsule_at(1, 0, 0).unwrap();
+    assert_eq!(late.state.status, CapsuleStatus::Running);
+    assert_eq!(late.state.wait, 5);
+}
+
+#[test]
+fn mailboxes_hold_at_most_sixteen_messages() {
+    let mut vm = LatticeVM::empty("mail".into());
+    // 17 SENDs of 1..=17 in one activation
+    vm.activate(capsule(1500, (0, 0, 0), "l: INC SEND 1 0 0 BNE 17 l HALT"))
+        .unwrap();
+    vm.activate(capsule(1501, (1, 0, 0), "YIELD POLL PUT R2 RECV HALT"))
+        .unwrap();
+
+    let events = run(&mut vm, 1);
+    let sent: Vec<_> = events
+        .iter()
+        .filter_map(|e| match e {
+            VmEvent::Sent {
+                from: 1500,
+                target: (1, 0, 0),
+                value,
+                delivered,
+            } => Some((*value, *delivered)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(sent.len(), 17);
+    assert!(sent[..16].iter().all(|&(_, delivered)| delivered));
+    assert_eq!(sent[16], (17, false));
+
+    // Oldest first: R0 = value, R1 = sender; R2 holds what POLL saw
+    run(&mut vm, 1);
+    let receiver = vm.capsule_at(1, 0, 0).unwrap();
+    assert_eq!(receiver.state.registers[..3], [1, 1500, 16]);
+    assert_eq!(receiver.state.mailbox.len(), 15);
+}
+
+#[test]
+fn call_stack_is_bounded() {
+    let mut vm = LatticeVM::empty("calls".into());
+    vm.activate(capsule(1500, (0, 0, 0), "CALL f LOG HALT f: INC RET"))
+        .unwrap();
+    // Recurses until the 17th CALL finds the stack full
+    vm.activate(capsule(1501, (1, 0, 0), "l: CALL l")).unwrap();
+    vm.activate(capsule(1502, (2, 0, 0), "RET")).unwrap();
+
+    let trapped = |capsule_id, trap| VmEvent::Trapped {
+        capsule_id,
+        ip: 0,
+        trap,
+        handled: false,
+    };
+    assert_eq!(
+        run(&mut vm, 1),
+        vec![
+            VmEvent::Logged {
+                capsule_id: 1500,
+                value: 1
+            },
+            trapped(1501, Trap::StackOverflow),
+            trapped(1502, Trap::StackUnderflow),
+        ]
+    );
+    assert_eq!(vm.capsule_at(1, 0, 0).unwrap().state.call_stack.len(), 16);
+    assert!(vm.capsule_at(0, 0, 0).unwrap().state.call_stack.is_empty());
+}
+
+#[test]
+fn alu_truncates_division_and_sets_flags() {
+    let mut vm = LatticeVM::empty("alu".into());
+    vm.activate(capsule(
+        1500,
+        (0, 0, 0),
+        "LDI R0 -20 LDI R1 7 MOV R2 R0 DIV R2 R1 MOV R3 R0 MOD R3 R1 CMP R3 R2 HALT",
+    ))
+    .unwrap();
+    vm.activate(capsule(
+        1501,
+        (1, 0, 0),
+        "LDI R0 -16 LDI R1 33 LDI R2 2 SHL R0 R1 SHR R0 R2 CMP R1 R1 LDI R3 0 DIV R0 R3",
+    ))
+    .unwrap();
+
+    let events = run(&mut vm, 1);
+    let first = vm.capsule_at(0, 0, 0).unwrap();
+    // -20 / 7 and -20 % 7 round toward zero; CMP R3 R2 is -6 < -2
+    assert_eq!(first.state.registers, [-20, 7, -2, -6]);
+    assert_eq!(first.state.flags, FLAG_LESS);
+
+    // SHL uses the shift modulo 32 and SHR keeps the sign; the DIV by zero
+    // traps and leaves R0 alone
+    let second = vm.capsule_at(1, 0, 0).unwrap();
+    assert_eq!(second.state.registers[0], -8);
+    assert_eq!(second.state.flags, FLAG_EQUAL);
+    assert_eq!(
+        events,
+        vec![VmEvent::Trapped {
+            capsule_id: 1501,
+            ip: 21,
+            trap: Trap::DivideByZero,
+            handled: false,
+        }]
+    );
+}
+
+#[test]
+fn sensing_reads_the_cycle_snapshot() {
+    let mut vm = LatticeVM::empty("sense".into());
+    vm.activate(capsule(
+        1500,
+        (10, -4, 2),
+        "GETX LOG GETY LOG GETZ LOG GETCYC LOG GETID LOG \
+         SENSE 1 0 0 LOG SENSE -1 0 0 LOG COUNTN 0 LOG COUNTN 1 LOG \
+         YIELD COUNTN 0 LOG HALT",
+    ))
+    .unwrap();
+    // A face neighbour, and an edge neighbour that spawns onto the other face
+    vm.activate(capsule(1501, (11, -4, 2), "HALT")).unwrap();
+    vm.activate(capsule(1502, (11, -3, 2), "SPAWN -2 -1 0 0 0 HALT"))
+        .unwrap();
+
+    let logged = |vm: &mut LatticeVM| -> Vec<i32> {
+        run(vm, 1)
+            .into_iter()
+            .filter_map(|e| match e {
+                VmEvent::Logged {
+                    capsule_id: 1500,
+                    value,
+                } => Some(value),
+                _ => None,
+            })
+            .collect()
+    };
+    // The child is born this cycle, so neither SENSE nor COUNTN sees it yet
+    assert_eq!(logged(&mut vm), [10, -4, 2, 1, 1500, 1, 0, 1, 2]);
+    assert_eq!(logged(&mut vm), [2]);
+}
@@ -0,0 +1,109 @@
+#![cfg(feature = "cli-mode")]
+
+mod common;
+
+use binling_core::capsules::{CapsuleRole, CapsuleStatus};
+use binling_core::config::{Occupancy, SchedulingMode, WritePolicy};
+use binling_core::error::VmError;
+use binling_core::vm::LatticeVM;
+use binling_core::world;
+use common::capsule;
+
+// A universe_*.bin written by the VM before universe files were versioned:
+// the Star Fortress plus user program 1000 (five INCs at the origin), three
+// cycles in. The shared register file holds R0 = 3 and program 1000 has
+// run three of its instructions.
+const BASELINE: &[u8] = include_bytes!("data/world_baseline.bin");
+
+fn round_trip(vm: &LatticeVM) -> LatticeVM {
+    let encoded = world::encode(vm).unwrap();
+    assert_eq!(encoded[0..4], world::WORLD_MAGIC);
+    assert_eq!(encoded[4..6], world::WORLD_VERSION.to_be_bytes());
+    world::decode(&encoded).unwrap()
+}
+
+#[test]
+fn pre_versioning_universe_migrates_and_round_trips() {
+    let vm = world::decode(BASELINE).unwrap();
+    assert_eq!(vm.universe_id, "baseline");
+    assert_eq!(vm.cycle_count, 3);
+    assert_eq!(vm.next_id, 1000);
+    assert_eq!(vm.next_queue.len(), 50);
+    assert!(vm.halted.is_none());
+    // The old runtime's rules
+    assert_eq!(vm.config.scheduling, SchedulingMode::Legacy);
+    assert_eq!(vm.config.write_policy, WritePolicy::FirstWriter);
+    assert_eq!(vm.config.instruction_budget, 1);
+    assert_eq!(vm.config.occupancy, Occupancy::Stacked);
+
+    let role = |id| {
+        vm.next_queue
+            .iter()
+            .find(|c| c.header.capsule_id == id)
+            .map(|c| c.state.role)
+    };
+    assert_eq!(role(1), Some(CapsuleRole::Kernel));
+    assert_eq!(role(100), Some(CapsuleRole::Structure));
+    assert_eq!(role(1000), Some(CapsuleRole::UserProgram));
+
+    let program = vm
+        .next_queue
+        .iter()
+        .find(|c| c.header.capsule_id == 1000)
+        .unwrap();
+    assert_eq!(program.state.ip, 3);
+    assert_eq!(program.header.pad_len, 0);
+    assert_eq!(program.state.registers, [3, 0, 0, 0]);
+    assert_eq!(program.state.status, CapsuleStatus::Running);
+
+    let mut migrated = vm.clone();
+    let mut reloaded = round_trip(&vm);
+    assert_eq!(reloaded.state_digest(), migrated.state_digest());
+    for _ in 0..3 {
+        migrated.next_cycle().unwrap();
+        reloaded.next_cycle().unwrap();
+    }
+    assert_eq!(reloaded.state_digest(), migrated.state_digest());
+    let program = reloaded
+        .next_queue
+        .iter()
+        .find(|c| c.header.capsule_id == 1000)
+        .unwrap();
+    assert_eq!(program.state.registers[0], 5);
+}
+
+#[test]
+fn decoded_universe_knows_which_cells_are_taken() {
+    let mut vm = LatticeVM::empty("index".into());
+    vm.activate(capsule(1500, (2, 0, 0), "HALT")).unwrap();
+
+    let mut reloaded = round_trip(&vm);
+    assert_eq!(
+        reloaded.capsule_at(2, 0, 0).map(|c| c.header.capsule_id),
+        Some(1500)
+    );
+    assert!(matches!(
+        reloaded.activate(capsule(1501, (2, 0, 0), "HALT")),
+        Err(VmError::Occupied { .. })
+    ));
+}
+
+#[test]
+fn halted_universe_keeps_its_halt() {
+    let mut vm = LatticeVM::empty("halted".into());
+    let halt = Some(VmError::WriteConflict {
+        target: (1, 2, 3),
+        idx: 4,
+        first_writer: 5,
+        second_writer: 6,
+    });
+    vm.halted = halt.clone();
+    assert_eq!(round_trip(&vm).halted, halt);
+}
+
+#[test]
+fn unknown_version_is_refused() {
+    let mut encoded = world::encode(&LatticeVM::empty("future".into())).unwrap();
+    encoded[4..6].copy_from_slice(&(world::WORLD_VERSION + 1).to_be_bytes());
+    assert!(world::decode(&encoded).is_err());
+}
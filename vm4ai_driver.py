@@ -31,22 +31,53 @@ Your goal is to translate the User's High-Level Intent into VALID BINLING ASSEMB
 - SUB              : R0 = R0 - R1
 - INC              : R0++
 - DEC              : R0--
-- BEQ val target   : Branch to 'target' index if R0 == val.
+- MOV dst src      : dst = src (registers R0-R3).
+- LDI reg n        : reg = n (n is -128..127).
+- MUL dst src      : dst = dst * src
+- DIV dst src      : dst = dst / src (dividing by zero is an error).
+- MOD dst src      : dst = dst % src (dividing by zero is an error).
+- AND/OR/XOR dst src : Bitwise dst = dst op src.
+- SHL/SHR dst src  : Shift dst left/right by src bits.
+- CMP a b          : Compare registers a and b and remember the result.
+- GET reg          : R0 = reg (reg is R0-R3).
+- PUT reg          : reg = R0 (reg is R0-R3).
+- BEQ val target   : Branch to 'target' index if R0 == val (val is -128..127).
+- BNE/BLT/BGT/BLE/BGE val target : Branch if R0 !=, <, >, <=, >= val.
+- BEQF/BNEF/BLTF/BGTF/BLEF/BGEF target : Branch on the last CMP a b (a ==, !=, <, >, <=, >= b).
 - JMP target       : Unconditional jump to 'target' index.
+- CALL target      : Jump to 'target' index, remembering where to come back.
+- RET              : Return to just after the last CALL.
 - REPL x y z       : REPLICATE self to neighbor (x,y,z).
-- SPAWN            : Create a child node (Not used in movement).
+- MOVE x y z       : Move self to neighbor (x,y,z), keeping id and registers (stays put if the cell is taken).
+- BBLK target      : Branch to 'target' if your last REPL, SPAWN or MOVE found its cell taken.
+- SPAWN x y z s n  : Create a new node at (x,y,z) running bytes s..s+n of own code (n=0: blank brick).
+- WAIT n           : Sleep for n cycles.
+- YIELD            : End this cycle's turn; continue from the next instruction next cycle.
+- ONFAULT target   : On the next error, jump to 'target' with the error number in R0 instead of stopping.
+- SEND x y z       : Send R0 as a message to the neighbor at (x,y,z); it arrives next cycle.
+- RECV             : Wait for a message: R0 = value, R1 = sender id.
+- POLL             : R0 = number of messages waiting.
+- GETX/GETY/GETZ   : R0 = own x / y / z coordinate.
+- GETID            : R0 = own capsule id.
+- GETCYC           : R0 = current cycle number.
+- SENSE x y z      : R0 = 1 if the neighbor at (x,y,z) exists, else 0.
+- COUNTN m         : R0 = number of neighbors (m=0: 6 faces, m=1: all 26 around).
+- ACTIVATE x y z   : Wake the dormant node at absolute (x,y,z) next cycle (x, y, z are -32768..32767).
+- ACTIVATE_NEIGHBOR x y z : Wake the dormant neighbor at relative (x,y,z) next cycle.
+- DORMANT          : Sleep until activated, then restart from the beginning.
 - HALT             : Stop execution.
+- name:            : Label the next instruction; use 'name' as a jump or call target.
 - VOID             : Delete self (Suicide).
 
 # CONSTRAINTS
 1. OUTPUT ONLY RAW ASSEMBLY. No markdown, no comments, no explanations.
 2. The VM is a 3D Lattice. Movement is relative (x, y, z).
 3. "Self-Replication" requires the REPL opcode.
-4. To "Move", you must REPL (copy forward) then VOID (delete behind).
+4. To "Move", use MOVE; it takes effect at the end of the cycle.
 
 # EXAMPLE
 User: "Create a sentinel that moves east forever."
-Output: LOAD 0 0 0 80 INC STORE 0 0 0 80 REPL 1 0 0 VOID HALT
+Output: MOVE 1 0 0 JMP 0
 """
 
 def ensure_interface():
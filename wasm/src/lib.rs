@@ -1,4 +1,9 @@
-use binling_core::capsules::{Capsule, CapsuleHeader, SquareSpace};
+use binling_core::capsules::{Capsule, CapsuleHeader, CapsuleRole, ExecState, SquareSpace};
+use binling_core::codec::HEADER_LEN;
+use binling_core::events::VmEvent;
+use binling_core::instructions::OpCode;
+use binling_core::integrity;
+use binling_core::scenario::Scenario;
 use binling_core::vm::LatticeVM;
 use wasm_bindgen::prelude::*;
 
@@ -10,56 +15,105 @@ pub fn main_js() -> Result<(), JsValue> {
     Ok(())
 }
 
+// One SPAWN per cycle (SPAWN ends the activation): lays blank bricks along the four diagonals of
+// the z = 0 plane, between the Star Fortress arms, then halts.
+fn diagonal_builder() -> Vec<u8> {
+    let mut payload = Vec::new();
+    for i in 1..=8i8 {
+        for (sx, sy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+            let (dx, dy) = (sx * i, sy * i);
+            payload.extend_from_slice(&[OpCode::SPAWN as u8, dx as u8, dy as u8, 0, 0, 0]);
+        }
+    }
+    payload.push(OpCode::HALT as u8);
+    payload
+}
+
+// The Star Fortress plus a kernel running diagonal_builder()
+fn default_world() -> LatticeVM {
+    // We now provide a default name for the browser instance
+    let mut vm = LatticeVM::new("web-local".to_string());
+
+    // Let's inject a "Genesis Capsule" just like in the CLI
+    let mut genesis = Capsule {
+        header: CapsuleHeader {
+            magic: *b"BLE1",
+            version_major: 0,
+            version_minor: 1,
+            flags: 0,
+            capsule_id: 777,
+            ss_n: SquareSpace::SS64,
+            priority: 10,
+            coord_x: 0,
+            coord_y: 0,
+            coord_z: 0,
+            header_len: HEADER_LEN as u16,
+            policy_len: 0,
+            payload_len: 0,
+            pad_len: 0,
+            dict_hash: [0; 32],
+            policy_core_hash: [0; 32],
+            capsule_hash: [0; 32],
+        },
+        policy_core: vec![],
+        payload: diagonal_builder(),
+        state: ExecState::default(),
+    };
+
+    integrity::seal(&mut genesis).expect("genesis capsule fits in SS64");
+    vm.activate_with_role(genesis, CapsuleRole::Kernel)
+        .expect("sealed genesis capsule verifies");
+    vm
+}
+
 // This struct will be exported to JavaScript class "WebLattice"
 #[wasm_bindgen]
 pub struct WebLattice {
     vm: LatticeVM,
+    last_events: Vec<VmEvent>, // What happened during the latest tick
+}
+
+impl Default for WebLattice {
+    fn default() -> Self {
+        Self::new(None).expect("default world builds")
+    }
 }
 
 #[wasm_bindgen]
 impl WebLattice {
-    // Constructor: JS calls "new WebLattice()"
+    // Constructor: JS calls "new WebLattice()" for the default world, or
+    // "new WebLattice(s)" with a built-in scenario name or scenario JSON.
     #[wasm_bindgen(constructor)]
-    pub fn new() -> WebLattice {
-        // --- FIX IS HERE ---
-        // We now provide a default name for the browser instance
-        let mut vm = LatticeVM::new("web-local".to_string());
-        // -------------------
-
-        // Let's inject a "Genesis Capsule" just like in the CLI
-        let genesis = Capsule {
-            header: CapsuleHeader {
-                magic: *b"BLE1",
-                version_major: 0,
-                version_minor: 1,
-                flags: 0,
-                capsule_id: 777,
-                ss_n: SquareSpace::SS64,
-                priority: 10,
-                coord_x: 0,
-                coord_y: 0,
-                coord_z: 0,
-                header_len: 122,
-                policy_len: 0,
-                payload_len: 10,
-                pad_len: 0,
-                dict_hash: [0; 32],
-                policy_core_hash: [0; 32],
-                capsule_hash: [0; 32],
-            },
-            policy_core: vec![],
-            payload: vec![7u8; 4096], // 4096 SPAWN commands
+    pub fn new(scenario: Option<String>) -> Result<WebLattice, JsValue> {
+        let mut vm = match scenario {
+            None => default_world(),
+            Some(text) => {
+                let scenario = if Scenario::BUILTIN.contains(&text.as_str()) {
+                    Scenario::builtin(&text)
+                } else {
+                    Scenario::from_json(&text)
+                };
+                scenario
+                    .and_then(|s| LatticeVM::with_scenario("web-local".to_string(), &s))
+                    .map_err(|e| JsValue::from_str(&e.to_string()))?
+            }
         };
 
-        vm.activate(genesis);
+        // Keep the last 1000 cycles reachable for step_back / seek / fork
+        vm.enable_history(10, 100);
 
-        WebLattice { vm }
+        Ok(WebLattice {
+            vm,
+            last_events: Vec::new(),
+        })
     }
 
     pub fn tick(&mut self) -> String {
-        if !self.vm.is_void() {
-            self.vm.next_cycle();
+        if !self.vm.is_void() && !self.vm.is_halted() {
+            // A failure is kept in vm.halted and exposed via get_halt_reason()
+            let _ = self.vm.next_cycle();
         }
+        self.last_events = self.vm.events.drain(..).collect();
 
         // We return a JSON string to JS (Simple serialization)
         // Note: For high performance, we would use shared memory, but this is fine for v1.
@@ -87,4 +141,43 @@ impl WebLattice {
     pub fn get_count(&self) -> usize {
         self.vm.next_queue.len()
     }
+
+    // JSON array of the events from the latest tick
+    pub fn get_events(&self) -> String {
+        serde_json::to_string(&self.last_events).unwrap_or("[]".to_string())
+    }
+
+    // Time travel. Events from the rewind are reported by get_events().
+    pub fn step_back(&mut self) -> Result<(), JsValue> {
+        self.vm
+            .step_back()
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.last_events = self.vm.events.drain(..).collect();
+        Ok(())
+    }
+
+    pub fn seek(&mut self, cycle: u64) -> Result<(), JsValue> {
+        self.vm
+            .seek(cycle)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.last_events = self.vm.events.drain(..).collect();
+        Ok(())
+    }
+
+    // A separate lattice continuing from `cycle`; this one is unchanged.
+    pub fn fork(&self, cycle: u64) -> Result<WebLattice, JsValue> {
+        let mut vm = self
+            .vm
+            .fork_at(cycle, "web-fork".to_string())
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        vm.enable_history(10, 100);
+        Ok(WebLattice {
+            vm,
+            last_events: Vec::new(),
+        })
+    }
+
+    pub fn get_halt_reason(&self) -> Option<String> {
+        self.vm.halted.as_ref().map(|e| e.to_string())
+    }
 }